@@ -1,5 +1,6 @@
 fn main() {
     if let Err(e) = wtf_rlsr::execute() {
-        println!("error: {:?}", e);
+        eprintln!("error: {:?}", e);
+        std::process::exit(1);
     }
 }
@@ -7,11 +7,14 @@ use failure::ResultExt;
 
 use crate::{
     git::{self, Repository},
-    util::{self, CleanPath},
+    ui::{Prompter, RecordingPrompter, ScriptedPrompter, TermPrompter},
+    util::{self, CleanPath, Config},
     ws::{self, Workspace},
 };
 
-use super::{release, update_paths, Command, Opt};
+use super::{
+    lint_deps, migrate_replace, pin_git, preflight, release, unpin, update_paths, Command, Opt,
+};
 
 /// Command execution runtime structure.
 pub struct ExecRuntime<'a> {
@@ -21,17 +24,27 @@ pub struct ExecRuntime<'a> {
     pub workspace: Workspace<'a>,
     pub head_branch: String,
     pub directory: PathBuf,
+    pub remote: String,
+    pub config: Config,
+    pub prompter: Box<dyn Prompter>,
 }
 
 impl<'a> ExecRuntime<'a> {
     pub fn open_cache_repo(&mut self) -> Result<Repository, git2::Error> {
-        git::init_cache_repo(&self.cache_dir, &self.directory, &self.head_branch)
+        git::init_cache_repo(
+            &self.cache_dir,
+            &self.directory,
+            &self.head_branch,
+            self.config.merge_strategy,
+            &mut git::RemoteAuth::new(&self.config.credentials, Some(&mut *self.prompter)),
+        )
     }
 }
 
 /// Executes a particular command.
 pub fn execute(opt: &Opt, cmd: &Command) -> Result<(), failure::Error> {
     let mut repo = Repository::open(&opt.directory)?;
+    repo.set_change_detection(opt.config.change_detection);
     let head_branch = git::get_head_branch(&repo)?;
     let cache_dir = std::fs::canonicalize(&opt.cache_dir)?.fix_path();
     trace!("Canonicalized cache directory: {:?}", cache_dir);
@@ -44,6 +57,17 @@ pub fn execute(opt: &Opt, cmd: &Command) -> Result<(), failure::Error> {
 
     let workspace = Workspace::new(&cargo, &mut repo)?;
 
+    let mut prompter: Box<dyn Prompter> = match &opt.answers {
+        Some(path) => Box::new(
+            ScriptedPrompter::from_file(path)
+                .with_context(|e| format!("Error reading answers from {:?}: {}", path, e))?,
+        ),
+        None => Box::new(TermPrompter),
+    };
+    if let Some(path) = &opt.record_answers {
+        prompter = Box::new(RecordingPrompter::new(prompter, path));
+    }
+
     let runtime = ExecRuntime {
         repo,
         cache_dir,
@@ -51,6 +75,9 @@ pub fn execute(opt: &Opt, cmd: &Command) -> Result<(), failure::Error> {
         workspace,
         head_branch,
         directory: opt.directory.clone(),
+        remote: opt.remote.clone(),
+        config: opt.config.clone(),
+        prompter,
     };
 
     match cmd {
@@ -59,10 +86,19 @@ pub fn execute(opt: &Opt, cmd: &Command) -> Result<(), failure::Error> {
             &release::Command {
                 skip_tests: true,
                 dry_run: true,
-                no_publish: true,
+                no_publish: false,
+                local_registry: Some(runtime.cache_dir.join("target").join("wtf-rlsr-registry")),
+                push_dry_run: true,
+                report: None,
+                report_file: None,
             },
             runtime,
         ),
         Command::UpdatePaths(cmd) => update_paths::execute(cmd, runtime),
+        Command::LintDeps(cmd) => lint_deps::execute(cmd, runtime),
+        Command::MigrateReplace(cmd) => migrate_replace::execute(cmd, runtime),
+        Command::PinGit(cmd) => pin_git::execute(cmd, runtime),
+        Command::Unpin(cmd) => unpin::execute(cmd, runtime),
+        Command::Preflight(cmd) => preflight::execute(cmd, runtime),
     }
 }
@@ -0,0 +1,156 @@
+//! Lint dependencies command.
+
+use cargo::core::dependency::Kind;
+use colored::Colorize;
+use structopt::StructOpt;
+
+use crate::{
+    parser::collect_members,
+    updater::Updater,
+    util::{self, CleanPath},
+    ws::Package,
+};
+
+use super::exec::ExecRuntime;
+
+/// Lint dependencies options.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "lint-deps",
+    about = "Reports unused and undeclared workspace dependencies."
+)]
+pub struct Command {
+    /// Fixes package manifests.
+    #[structopt(long = "fix")]
+    pub fix: bool,
+}
+
+/// Workspace dependencies lints of a package.
+struct Lints<'a> {
+    package: &'a Package<'a>,
+    /// Manifest keys of unused dependencies.
+    unused: Vec<String>,
+    /// Packages used in library and binary code.
+    undeclared: Vec<String>,
+    /// Packages used only in test code.
+    undeclared_dev: Vec<String>,
+}
+
+impl Lints<'_> {
+    fn is_empty(&self) -> bool {
+        self.unused.is_empty() && self.undeclared.is_empty() && self.undeclared_dev.is_empty()
+    }
+}
+
+/// Executes a `lint-deps` command.
+pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::Error> {
+    let mut lints = Vec::new();
+    for package in runtime.workspace.packages().iter() {
+        let members = collect_members(&runtime.workspace, package, &runtime.config.disabled_cfgs)?;
+        for diagnostic in &members.diagnostics {
+            println!("{} {}", "warning:".yellow(), diagnostic);
+        }
+        // Dependencies are keyed by name in manifest to handle renames
+        let declared: Vec<(String, String, Kind)> = package
+            .dependencies()
+            .iter()
+            .filter(|dep| {
+                runtime
+                    .workspace
+                    .find_package(&dep.package_name())
+                    .is_some()
+            })
+            .map(|dep| {
+                let key = dep.name_in_toml().to_string();
+                (key, dep.package_name().to_string(), dep.kind())
+            })
+            .collect();
+        let is_declared = |name: &str, dev: bool| {
+            declared
+                .iter()
+                .any(|(_, pkg, kind)| pkg == name && (dev || *kind != Kind::Development))
+        };
+        let not_self = |name: &&String| name.as_str() != package.name().as_str();
+        let used: Vec<&String> = members
+            .packages
+            .iter()
+            .chain(members.macros.iter())
+            .filter(not_self)
+            .collect();
+        let used_test: Vec<&String> = members.test_packages.iter().filter(not_self).collect();
+        let mut unused: Vec<String> = declared
+            .iter()
+            .filter(|(_, pkg, _)| !used.contains(&pkg) && !used_test.contains(&pkg))
+            .map(|(key, _, _)| key.clone())
+            .collect();
+        let mut undeclared: Vec<String> = used
+            .into_iter()
+            .filter(|name| !is_declared(name, false))
+            .cloned()
+            .collect();
+        let mut undeclared_dev: Vec<String> = used_test
+            .into_iter()
+            .filter(|name| !is_declared(name, true))
+            .cloned()
+            .collect();
+        unused.sort();
+        unused.dedup();
+        undeclared.sort();
+        undeclared.dedup();
+        undeclared_dev.sort();
+        undeclared_dev.dedup();
+        lints.push(Lints {
+            package,
+            unused,
+            undeclared,
+            undeclared_dev,
+        });
+    }
+
+    let issues: Vec<&Lints> = lints.iter().filter(|lint| !lint.is_empty()).collect();
+    if issues.is_empty() {
+        println!("No dependency issues in {}", runtime.workspace.name());
+        return Ok(());
+    }
+    for lint in &issues {
+        println!("{}:", lint.package.name().to_string().bold());
+        for name in &lint.unused {
+            println!("  * {} {}", "unused".yellow(), name);
+        }
+        for name in &lint.undeclared {
+            println!("  * {} {}", "undeclared".red(), name);
+        }
+        for name in &lint.undeclared_dev {
+            println!("  * {} {}", "undeclared dev".red(), name);
+        }
+    }
+    if !cmd.fix {
+        return Err(format_err!(
+            "{} packages with dependency issues",
+            issues.len()
+        ));
+    }
+
+    let mut updater = Updater::new(&mut runtime.repo)?;
+    for lint in issues {
+        let pkg_path = runtime.repo.rel_path(lint.package.directory());
+        let manifests = updater.manifests(lint.package, &mut runtime.repo)?;
+        for key in &lint.unused {
+            manifests.remove_dep(key);
+        }
+        let undeclared = lint.undeclared.iter().map(|name| (name, false));
+        let undeclared_dev = lint.undeclared_dev.iter().map(|name| (name, true));
+        for (name, dev) in undeclared.chain(undeclared_dev) {
+            let dep_pkg = runtime.workspace.find_package(name).unwrap();
+            let dep_path = runtime.repo.rel_path(dep_pkg.directory());
+            let rel_path = pathdiff::diff_paths(&dep_path, &pkg_path)
+                .unwrap()
+                .clean_path();
+            manifests.add_dep(name, dep_pkg.version(), &rel_path, dev);
+        }
+        manifests.save_preview()?;
+        util::rename(manifests.index_preview_path(), &manifests.manifest_path)?;
+        util::commit::remove_previews(&manifests.manifest_path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Migrate replace command.
+
+use structopt::StructOpt;
+
+use crate::{updater::Updater, util};
+
+use super::exec::ExecRuntime;
+
+/// Migrate replace options.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "migrate-replace",
+    about = "Converts workspace `[replace]` section into `[patch]`."
+)]
+pub struct Command {
+    /// Saves results to preview file.
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Registry to patch.
+    #[structopt(long = "registry", default_value = "crates-io")]
+    pub registry: String,
+}
+
+/// Executes a `migrate-replace` command.
+pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::Error> {
+    let mut updater = Updater::new(&mut runtime.repo)?;
+    let migrated = updater.workspace.migrate_replace(&cmd.registry)?;
+    if migrated.is_empty() {
+        println!("No replace entries in {}", runtime.workspace.name());
+        return Ok(());
+    }
+    for name in &migrated {
+        println!("  * {} -> patch.{}", name, cmd.registry);
+    }
+    updater.workspace.save_preview()?;
+    if !cmd.dry_run {
+        util::rename(
+            updater.workspace.index_preview_path(),
+            runtime.workspace.manifest_path(),
+        )?;
+        util::commit::remove_previews(runtime.workspace.manifest_path())?;
+    }
+    Ok(())
+}
@@ -1,12 +1,20 @@
 pub(crate) mod exec;
+pub(crate) mod lint_deps;
+pub(crate) mod migrate_replace;
+pub(crate) mod pin_git;
+pub(crate) mod preflight;
 pub(crate) mod release;
+pub(crate) mod unpin;
 pub(crate) mod update_paths;
 
 use std::path::PathBuf;
 
 use structopt::StructOpt;
 
-use crate::util::init::setup_opt;
+use crate::{
+    git::MergeStrategy,
+    util::{init::setup_opt, Config},
+};
 
 /// Command line application options.
 #[derive(Debug, StructOpt)]
@@ -24,9 +32,29 @@ pub struct Opt {
     #[structopt(short = "r", default_value = "origin")]
     pub remote: String,
 
+    /// Detects packages changed since their last release.
+    #[structopt(long = "since-release")]
+    pub since_release: bool,
+
+    /// Cache repository merge conflict strategy (`abort`, `reset` or `ours`).
+    #[structopt(long = "merge-strategy")]
+    pub merge_strategy: Option<MergeStrategy>,
+
+    /// Reads answers to prompts from a JSON file.
+    #[structopt(parse(from_os_str), long = "answers")]
+    pub answers: Option<PathBuf>,
+
+    /// Records answers to prompts to a JSON file.
+    #[structopt(parse(from_os_str), long = "record-answers")]
+    pub record_answers: Option<PathBuf>,
+
     /// Wtf-rlsr subcommand.
     #[structopt(subcommand)]
     pub cmd: Option<Command>,
+
+    /// Workspace configuration.
+    #[structopt(skip)]
+    pub config: Config,
 }
 
 /// Wtf-rlsr subcommand.
@@ -40,6 +68,21 @@ pub enum Command {
 
     /// Update command
     UpdatePaths(update_paths::Command),
+
+    /// Lint dependencies command.
+    LintDeps(lint_deps::Command),
+
+    /// Migrate replace command.
+    MigrateReplace(migrate_replace::Command),
+
+    /// Pin git command.
+    PinGit(pin_git::Command),
+
+    /// Unpin command.
+    Unpin(unpin::Command),
+
+    /// Pre-flight command.
+    Preflight(preflight::Command),
 }
 
 /// Executes a command.
@@ -0,0 +1,130 @@
+//! Pin git command.
+
+use std::path::{Path, PathBuf};
+
+use structopt::StructOpt;
+
+use crate::{
+    updater::{ManifestExt, WorkspaceManifest, WorkspaceManifestExt},
+    ws::{Package, Workspace},
+};
+
+use super::exec::ExecRuntime;
+
+/// Pin git options.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "pin-git",
+    about = "Pins workspace packages to a git repository revision."
+)]
+pub struct Command {
+    /// Packages to pin, all workspace packages if empty.
+    pub packages: Vec<String>,
+
+    /// Git repository URL, defaults to remote URL.
+    #[structopt(long = "url")]
+    pub url: Option<String>,
+
+    /// Git revision, defaults to HEAD commit.
+    #[structopt(long = "rev")]
+    pub rev: Option<String>,
+
+    /// Writes pins to `[patch.<registry>]` instead of `[replace]`.
+    #[structopt(long = "patch")]
+    pub patch: Option<String>,
+
+    /// Downstream manifest to write pins to.
+    #[structopt(parse(from_os_str), long = "manifest")]
+    pub manifest: PathBuf,
+
+    /// Saves results to preview file.
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+/// Executes a `pin-git` command.
+pub fn execute(cmd: &Command, runtime: ExecRuntime) -> Result<(), failure::Error> {
+    check_downstream(&runtime.workspace, &cmd.manifest)?;
+    let packages = select_packages(&runtime.workspace, &cmd.packages)?;
+    let url = match &cmd.url {
+        Some(url) => url.clone(),
+        None => runtime
+            .repo
+            .find_remote(&runtime.remote)?
+            .url()
+            .ok_or_else(|| format_err!("remote {} has no URL", runtime.remote))?
+            .to_owned(),
+    };
+    let rev = match &cmd.rev {
+        Some(rev) => rev.clone(),
+        None => runtime.repo.head_commit()?.id().to_string(),
+    };
+    let pin = |manifest: &mut WorkspaceManifest| {
+        for package in &packages {
+            manifest.remove_overrides(package, "git");
+            match &cmd.patch {
+                Some(registry) => manifest.git_patch(registry, package, &url, &rev),
+                None => manifest.git_replace(package, &url, &rev),
+            }
+        }
+    };
+    for package in &packages {
+        println!("  * {} -> {}#{}", package.name(), url, rev);
+    }
+    save_manifest(&cmd.manifest, pin, cmd.dry_run)
+}
+
+/// Checks manifest is outside of workspace,
+/// workspace packages can't be pinned to their own revisions.
+pub(crate) fn check_downstream(workspace: &Workspace, path: &Path) -> Result<(), failure::Error> {
+    let manifest =
+        std::fs::canonicalize(path).map_err(|e| format_err!("Manifest {:?} error: {}", path, e))?;
+    let packages = workspace.packages();
+    let manifests = std::iter::once(workspace.manifest_path())
+        .chain(packages.iter().map(|pkg| pkg.manifest_path()));
+    for member in manifests {
+        if std::fs::canonicalize(member).ok().as_ref() == Some(&manifest) {
+            return Err(format_err!(
+                "manifest {:?} belongs to workspace {}",
+                path,
+                workspace.name()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Selects workspace packages by name, all packages if names are empty.
+pub(crate) fn select_packages<'a>(
+    workspace: &'a Workspace<'a>,
+    names: &[String],
+) -> Result<Vec<&'a Package<'a>>, failure::Error> {
+    if names.is_empty() {
+        return Ok(workspace.packages().iter().collect());
+    }
+    names
+        .iter()
+        .map(|name| {
+            workspace
+                .find_package(name)
+                .ok_or_else(|| format_err!("package {} not found in workspace", name))
+        })
+        .collect()
+}
+
+/// Changes a manifest outside of workspace and saves it
+/// or its preview on dry run.
+pub(crate) fn save_manifest<F>(path: &Path, change: F, dry_run: bool) -> Result<(), failure::Error>
+where
+    F: Fn(&mut WorkspaceManifest),
+{
+    let mut manifest = WorkspaceManifest::new_index(path)?;
+    change(&mut manifest);
+    let dest = if dry_run {
+        path.with_file_name("Cargo.preview-index.toml")
+    } else {
+        path.to_path_buf()
+    };
+    manifest.save(&dest)?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Pre-flight command.
+
+use structopt::StructOpt;
+
+use crate::{
+    git, updater,
+    util::{self, BumpExt, Update},
+};
+
+use super::exec::ExecRuntime;
+use super::release::release_tags;
+
+/// Pre-flight options.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "preflight", about = "Runs release pre-flight checks.")]
+pub struct Command {
+    /// Package to release, all changed packages if empty.
+    #[structopt(long = "package", short = "p")]
+    pub package: Option<String>,
+
+    /// Update kind (`docs`, `chore`, `patch`, `minor` or `major`).
+    #[structopt(long = "update", short = "u", default_value = "patch")]
+    pub update: Update,
+
+    /// Skips publish token and registry checks.
+    #[structopt(long = "no-publish")]
+    pub no_publish: bool,
+}
+
+/// Executes a `preflight` command.
+pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::Error> {
+    let mut targets = Vec::new();
+    match &cmd.package {
+        Some(name) => {
+            let package = runtime
+                .workspace
+                .find_package(name)
+                .ok_or_else(|| format_err!("package {} not found in workspace", name))?;
+            if let Some(bump) = cmd.update.as_bump() {
+                targets.push((package.name().to_string(), package.version().bump(bump)));
+                // Assumes changed dependants are committed with the package
+                for dep in updater::collect_dependants(&runtime.workspace, package) {
+                    let dep_bump = bump.dependency(dep.is_changed(), dep.is_changed());
+                    targets.push((dep.name().to_string(), dep.version().bump(dep_bump)));
+                }
+            }
+        }
+        None => {
+            for package in runtime.workspace.packages().iter() {
+                if package.is_changed() {
+                    let version = cmd.update.bump(package.version());
+                    targets.push((package.name().to_string(), version));
+                }
+            }
+        }
+    }
+    for (name, version) in &targets {
+        println!("  * {} v{}", name, version);
+    }
+    let tags = release_tags(&runtime.workspace, &cmd.update, &targets);
+    let checks = util::Preflight {
+        repo: &runtime.repo,
+        workspace: &runtime.workspace,
+        cargo_config: runtime.cargo.config(),
+        cache_dir: &runtime.cache_dir,
+        remote: &runtime.remote,
+        branch: &runtime.head_branch,
+        targets: &targets,
+        tags: &tags,
+        publish: cmd.update.as_bump().is_some() && !cmd.no_publish,
+        offline: false,
+    }
+    .run(&mut git::RemoteAuth::new(
+        &runtime.config.credentials,
+        Some(&mut *runtime.prompter),
+    ));
+    util::report_preflight(&checks)
+}
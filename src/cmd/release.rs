@@ -1,15 +1,15 @@
 //! Release command.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use colored::Colorize;
 use structopt::StructOpt;
 
 use crate::{
-    git::{self, CommitBuilder},
-    ui,
+    git::{self, CommitBuilder, Signer},
+    parser, ui,
     updater::{self, ManifestExt, Updater},
-    util::{self, BumpExt, Logger},
+    util::{self, BumpExt, Logger, Report, ReportFormat, Update},
     ws::{self, Workspace},
 };
 
@@ -30,49 +30,123 @@ pub struct Command {
     /// Publish dry run.
     #[structopt(long = "dry-run")]
     pub dry_run: bool,
+
+    /// Publishes packages to a local registry in a directory.
+    #[structopt(parse(from_os_str), long = "local-registry")]
+    pub local_registry: Option<PathBuf>,
+
+    /// Reports what would be pushed without pushing or publishing.
+    #[structopt(long = "push-dry-run")]
+    pub push_dry_run: bool,
+
+    /// Prints release report to stdout (`json` or `md`),
+    /// other release output is printed to stderr.
+    #[structopt(long = "report")]
+    pub report: Option<ReportFormat>,
+
+    /// Writes release report to a file.
+    #[structopt(parse(from_os_str), long = "report-file")]
+    pub report_file: Option<PathBuf>,
+}
+
+impl Command {
+    /// Writes release report to a file or stdout.
+    fn write_report(&self, report: &Report) -> Result<(), failure::Error> {
+        if let Some(path) = &self.report_file {
+            let format = self.report.unwrap_or_else(|| ReportFormat::from_path(path));
+            std::fs::write(path, report.render(format)?)?;
+        } else if let Some(format) = self.report {
+            println!("{}", report.render(format)?);
+        }
+        Ok(())
+    }
 }
 
 /// Executes a `release` command.
 pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::Error> {
+    let mut report = Report::default();
+    let result = release(cmd, &mut runtime, &mut report);
+    // Previews are regenerated by every run, backups are kept to restore failed releases
+    for path in manifest_paths(&runtime.workspace) {
+        if let Err(err) = util::commit::remove_previews(&path) {
+            report.warn(format!("Error removing preview manifests: {}", err));
+        }
+    }
+    report.finish_stage();
+    if let Err(err) = &result {
+        report.warn(format!("Release failed: {}", err));
+    }
+    if let Err(err) = cmd.write_report(&report) {
+        if result.is_ok() {
+            return Err(err);
+        }
+        error!("Error writing release report: {}", err);
+    }
+    result
+}
+
+fn release(
+    cmd: &Command,
+    runtime: &mut ExecRuntime,
+    report: &mut Report,
+) -> Result<(), failure::Error> {
+    report.stage("Selecting");
     if runtime.workspace.packages().changed() == 0 {
-        println!(
-            "No changed packages in {} v{}",
-            runtime.workspace.name(),
-            runtime.workspace.version()
-        );
+        match runtime.workspace.version() {
+            Some(version) => eprintln!(
+                "No changed packages in {} v{}",
+                runtime.workspace.name(),
+                version
+            ),
+            None => eprintln!("No changed packages in {}", runtime.workspace.name()),
+        }
         return Ok(());
     }
 
-    // Open cache repo
-    let mut cache_repo = runtime.open_cache_repo()?;
-
     // Select main package to release
-    let package = match ui::packages::select_changed(&runtime.workspace)? {
+    let package = match ui::packages::select_changed(&mut *runtime.prompter, &runtime.workspace)? {
         Some(package) => package,
         None => return Ok(()),
     };
+    report.root_package = Some(package.name().to_string());
+
+    // Detect public API changes
+    let api = match parser::api_diff(&runtime.repo, package, &runtime.config.disabled_cfgs) {
+        Ok(api) => Some(api),
+        Err(err) => {
+            report.warn(format!("API change detection failed: {}", err));
+            None
+        }
+    };
 
     // Select update kind
-    let update = match ui::update::prompt(package)? {
+    let update = match ui::update::prompt(&mut *runtime.prompter, package, api.as_ref())? {
         Some(bump) => bump,
         None => return Ok(()),
     };
+    report.update = Some(update.name().to_owned());
 
     // Get new version of a package (or the same if not bumped)
     let new_pkg_ver = update.bump(package.version());
+    // Released packages with new versions
+    let mut released = Vec::new();
+    if update.as_bump().is_some() {
+        report.bumped(package.name().as_str(), package.version(), &new_pkg_ver);
+        released.push((package.name().to_string(), new_pkg_ver.clone()));
+    }
 
     // Get all dependandts of the package
     let dependants = updater::collect_dependants(&runtime.workspace, package);
 
-    println!("Packages affected by update:");
+    eprintln!("Packages affected by update:");
     for pkg in &dependants {
         if !pkg.is_changed() {
-            println!("  * {}", pkg.name().to_string().yellow());
+            eprintln!("  * {}", pkg.name().to_string().yellow());
         } else {
-            println!("  * {}", pkg.name().to_string().red());
+            eprintln!("  * {}", pkg.name().to_string().red());
         }
     }
-    println!();
+    eprintln!();
 
     let (commit_packages, _) = {
         let changed: Vec<_> = dependants
@@ -80,7 +154,13 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
             .filter(|p| p.is_changed())
             .map(|p| *p)
             .collect();
-        ui::packages::select_packages("Commit changes of dependencies", &changed, &update, false)
+        ui::packages::select_packages(
+            &mut *runtime.prompter,
+            "Commit changes of dependencies",
+            &changed,
+            &update,
+            false,
+        )?
     };
 
     let (tree_packages, _) = {
@@ -90,28 +170,76 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
             .map(|p| *p)
             .collect();
         ui::packages::select_packages(
+            &mut *runtime.prompter,
             "Select dependencies to update in tree",
             &not_commit,
             &update,
             true,
-        )
+        )?
     };
 
     let update_packages = [commit_packages.as_slice(), tree_packages.as_slice()].concat();
 
-    if ui::confirm("Do you want to see git diff?")? {
+    // Dependants released with new versions
+    if let Some(bump) = update.as_bump() {
+        for pkg_dep in update_packages.iter() {
+            let is_commit = commit_packages.contains(pkg_dep);
+            let new_dep_ver = pkg_dep
+                .version()
+                .bump(bump.dependency(pkg_dep.is_changed(), is_commit));
+            released.push((pkg_dep.name().to_string(), new_dep_ver));
+        }
+    }
+
+    // Check repository, remote and registry before any side effect
+    report.stage("Pre-flight");
+    let rls_tags = release_tags(&runtime.workspace, update, &released);
+    let checks = util::Preflight {
+        repo: &runtime.repo,
+        workspace: &runtime.workspace,
+        cargo_config: runtime.cargo.config(),
+        cache_dir: &runtime.cache_dir,
+        remote: &runtime.remote,
+        branch: &runtime.head_branch,
+        targets: &released,
+        tags: &rls_tags,
+        publish: update.as_bump().is_some()
+            && !cmd.no_publish
+            && !cmd.push_dry_run
+            && !cmd.dry_run
+            && cmd.local_registry.is_none(),
+        offline: cmd.dry_run,
+    }
+    .run(&mut git::RemoteAuth::new(
+        &runtime.config.credentials,
+        Some(&mut *runtime.prompter),
+    ));
+    util::report_preflight(&checks)?;
+
+    // Open cache repo
+    let mut cache_repo = runtime.open_cache_repo()?;
+
+    if ui::confirm(&mut *runtime.prompter, "Do you want to see git diff?")? {
         let opts = util::diff2html::Options::default();
         let diff_pkgs = &[&[package], commit_packages.as_slice()].concat();
         util::diff2html::spawn_for_pkgs(diff_pkgs.as_slice(), &opts)?;
-        if !ui::confirm("Do you want to continue?")? {
+        if !ui::confirm(&mut *runtime.prompter, "Do you want to continue?")? {
             return Ok(());
         }
     }
 
-    let header = ui::commit::prompt_header("Commit header")?;
-    let message = ui::commit::prompt("Commit message")?;
+    let messages = &runtime.config.messages;
+    let header = ui::commit::prompt_header(
+        &mut *runtime.prompter,
+        "Commit header",
+        messages.header_min,
+        messages.header_max,
+    )?;
+    let message = ui::commit::prompt(&mut *runtime.prompter, "Commit message")?;
 
+    report.stage("Updating manifests");
     let mut updater = Updater::new(&mut runtime.repo)?;
+    updater.policy = runtime.config.requirement_policy;
 
     // Update package version using Updater.
     // It will save preview manifest files.
@@ -140,7 +268,10 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
     }
 
     // Start building a commit for changed package
+    let signer = Signer::from_config(&runtime.repo, &runtime.config.signing)?;
     let mut commit = CommitBuilder::new(&mut runtime.repo)?;
+    commit.configure(&runtime.config.commit)?;
+    commit.signer = signer.clone();
     util::commit::add_diff(
         &mut commit,
         package.diff.as_ref().unwrap(),
@@ -165,22 +296,24 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
         for pkg_dep in update_packages.iter() {
             let is_commit = commit_packages.contains(pkg_dep);
             let pkg_dep_bump = bump.dependency(pkg_dep.is_changed(), is_commit);
+            let new_dep_ver = pkg_dep.version().bump(pkg_dep_bump);
+            report.bumped(pkg_dep.name().as_str(), pkg_dep.version(), &new_dep_ver);
             // Bump replace in cargo workspace manifest.
             updater.workspace.bump_replace_ver(pkg_dep, pkg_dep_bump);
 
             // let cargo_toml = repo.get_contents(tree, pkg_dep)
             let manifest = updater.manifests(pkg_dep, &mut runtime.repo)?;
             manifest.bump_ver(pkg_dep_bump);
-            manifest.update_dep(package.name().as_str(), package.version(), &new_pkg_ver);
+            manifest.update_dep(package.name().as_str(), package.version(), &new_pkg_ver)?;
 
-            update_packages.iter().for_each(|deep_dep| {
+            for deep_dep in update_packages.iter() {
                 let name = deep_dep.name().as_str();
                 let is_commit = commit_packages.contains(pkg_dep);
                 let new_deep_dep_ver = deep_dep
                     .version()
                     .bump(bump.dependency(deep_dep.is_changed(), is_commit));
-                manifest.update_dep(name, deep_dep.version(), &new_deep_dep_ver);
-            });
+                manifest.update_dep(name, deep_dep.version(), &new_deep_dep_ver)?;
+            }
             // Save package manifest in `Cargo.preview-head.toml`.
             manifest.save_preview()?;
             // Copy package manifest to cached repository.
@@ -195,7 +328,9 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
     let cache_cargo = match ws::cargo_workspace(&cache_config) {
         Ok(cargo) => cargo,
         Err(err) => {
-            println!("Error opening workspace: {:?}", err);
+            eprintln!("Error opening workspace: {:?}", err);
+            report.warn(format!("Error opening cached workspace: {}", err));
+            util::commit::restore_manifests(&manifest_paths(&runtime.workspace))?;
             return Ok(());
         }
     };
@@ -205,42 +340,121 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
     // Get package in cached workspace
     let cached_pkg = cache_workspace
         .find_package(package.name().as_str())
-        .unwrap();
+        .ok_or_else(|| format_err!("package {} not found in cached workspace", package.name()))?;
     // Run package tests if they are enabled
+    report.stage("Testing");
     if update.as_bump().is_some() && !cmd.skip_tests && !util::run_tests(cached_pkg, &cache_cargo)?
     {
-        util::commit::restore_manifest(package.manifest_path())?;
-        util::commit::restore_manifest(runtime.workspace.manifest_path())?;
+        report.warn(format!("Package {} tests failed", package.name()));
+        util::commit::restore_manifests(&manifest_paths(&runtime.workspace))?;
         return Ok(());
     }
 
-    // Start publishing to crates.io
-    if update.as_bump().is_some() && !cmd.no_publish {
+    // Check remote before publishing anything
+    if !cmd.dry_run {
+        report.stage("Checking remote");
+        let rls_tags = release_tags(&runtime.workspace, update, &released);
+        let mut auth =
+            git::RemoteAuth::new(&runtime.config.credentials, Some(&mut *runtime.prompter));
+        let checked = util::check_remote_branch(
+            &runtime.repo,
+            &runtime.remote,
+            &runtime.head_branch,
+            &mut auth,
+        )
+        .and_then(|_| {
+            util::check_remote_tags(&runtime.repo, &runtime.remote, &rls_tags, &mut auth)
+        });
+        if let Err(err) = checked {
+            util::commit::restore_manifests(&manifest_paths(&runtime.workspace))?;
+            return Err(err);
+        }
+    }
+
+    // Start publishing to crates.io, crates are not published without a push
+    if update.as_bump().is_some() && !cmd.no_publish && !cmd.push_dry_run {
+        report.stage("Publishing");
         runtime.cargo.status("Publishing", "Starting");
+        let registry = match &cmd.local_registry {
+            Some(path) => Some(util::LocalRegistry::open(path)?),
+            None => None,
+        };
+        let target = match &registry {
+            Some(registry) => util::PublishTarget::Local(registry),
+            None => util::PublishTarget::Registry {
+                dry_run: cmd.dry_run,
+            },
+        };
         let mut published = Vec::new();
-        if !util::publish_pkg_deep(
+        match util::publish_pkg_deep(
             &cached_pkg,
             &cache_workspace,
             &cache_config,
             &update_packages,
             &mut published,
-            cmd.dry_run,
-        )? {
-            util::commit::restore_manifest(package.manifest_path())?;
-            util::commit::restore_manifest(runtime.workspace.manifest_path())?;
-            return Ok(());
+            report,
+            target,
+        ) {
+            Ok(true) => {}
+            Ok(false) => {
+                util::commit::restore_manifests(&manifest_paths(&runtime.workspace))?;
+                return Ok(());
+            }
+            Err(err) => {
+                util::commit::restore_manifests(&manifest_paths(&runtime.workspace))?;
+                return Err(err);
+            }
         }
         runtime.cargo.status("Publishing", "Done");
+
+        // Resolve and build published packages against local registry
+        if let Some(registry) = &registry {
+            report.stage("Verifying registry");
+            runtime.cargo.status(
+                "Verifying",
+                format!("local registry {}", registry.root().display()),
+            );
+            let checked = std::iter::once(cached_pkg.name().to_string())
+                .chain(published)
+                .map(|name| match cache_workspace.find_package(&name) {
+                    Some(pkg) => Ok((name, pkg.version().clone())),
+                    None => Err(format_err!(
+                        "package {} not found in cached workspace",
+                        name
+                    )),
+                })
+                .collect::<Result<Vec<_>, failure::Error>>()
+                .and_then(|packages| registry.check(&packages));
+            if let Err(err) = checked {
+                report.warn(format!("Local registry check failed: {}", err));
+                util::commit::restore_manifests(&manifest_paths(&runtime.workspace))?;
+                return Err(err);
+            }
+        }
         runtime.cargo.status("Committing", "Starting");
     }
 
+    report.stage("Committing");
     if !cmd.dry_run {
-        let commit_message =
-            util::commit::message(package, update, &header, message.as_ref(), false);
-        commit.commit(commit_message.trim(), &mut runtime.repo)?;
+        let commit_message = util::commit::message(
+            &runtime.config.messages,
+            package,
+            update,
+            &header,
+            message.as_ref(),
+            &commit_packages,
+            false,
+        );
+        if runtime.config.commit.released_packages {
+            commit.trailers.push(released_trailer(&released));
+        }
+        let oid = commit.commit(commit_message.trim(), &mut runtime.repo)?;
+        report.commits.push(oid.to_string());
     }
 
     let mut commit = CommitBuilder::new(&mut runtime.repo)?;
+    commit.configure(&runtime.config.commit)?;
+    commit.signer = signer;
 
     if !cmd.dry_run {
         for dep in tree_packages.iter() {
@@ -249,6 +463,15 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
         }
     }
 
+    // Bump workspace version kept in workspace manifest
+    if let (Some(bump), Some(version), Some(table)) = (
+        update.as_bump(),
+        runtime.workspace.version(),
+        runtime.workspace.version_source().manifest_table(),
+    ) {
+        updater.workspace.bump_workspace_ver(table, version, bump);
+    }
+
     // Save manifest in workspace
     updater.workspace.save_preview()?;
     // Save manifest in cached repo for testing purposes
@@ -263,24 +486,92 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
 
     if !cmd.dry_run {
         if let Some(bump) = update.as_bump() {
-            runtime.workspace.bump(bump)?;
-            let path = Path::new("package.json");
-            commit.add_path(&path)?;
-            let package_json = runtime.directory.join("package.json");
-            let cache_pkg_json = runtime.cache_dir.join("package.json");
-            util::copy(&package_json, cache_pkg_json)?;
+            // Bump workspace version kept in `package.json` or `VERSION` file
+            if let Some(path) = runtime.workspace.bump(bump)? {
+                commit.add_path(&path)?;
+                util::copy(runtime.directory.join(&path), runtime.cache_dir.join(&path))?;
+            }
+        }
+        let commit_message = util::commit::message(
+            &runtime.config.messages,
+            package,
+            update,
+            &header,
+            message.as_ref(),
+            &tree_packages,
+            true,
+        );
+        if runtime.config.commit.released_packages {
+            commit.trailers.push(released_trailer(&released));
+        }
+        let oid = commit.commit(commit_message.trim(), &mut runtime.repo)?;
+        report.commits.push(oid.to_string());
+        // Release is committed, backed up manifests are not needed anymore
+        for path in manifest_paths(&runtime.workspace) {
+            util::commit::remove_backup(&path)?;
         }
-        let commit_message =
-            util::commit::message(package, update, &header, message.as_ref(), true);
-        commit.commit(commit_message.trim(), &mut runtime.repo)?;
-        let rls_tag = format!("refs/tags/v{}", update.bump(runtime.workspace.version()));
+        let rls_tags = release_tags(&runtime.workspace, update, &released);
         let branch_tag = format!("refs/heads/{}", runtime.head_branch);
-        git::set_head_ref(&rls_tag, &mut runtime.repo)?;
-        git::push_remote(&runtime.repo, "origin", &[&branch_tag, &rls_tag])?;
+        let mut refspecs = vec![branch_tag.as_str()];
+        refspecs.extend(rls_tags.iter().map(|tag| tag.as_str()));
+        if cmd.push_dry_run {
+            // Tags are neither created nor pushed, commit stays local
+            eprintln!("Would tag and push to {}:", runtime.remote);
+            for refspec in &refspecs {
+                eprintln!("  * {}", refspec);
+            }
+            report.warn(format!(
+                "Push dry run, commit {} was not pushed to {}",
+                oid, runtime.remote
+            ));
+        } else {
+            for rls_tag in &rls_tags {
+                git::set_head_ref(rls_tag, &mut runtime.repo)?;
+            }
+            report.stage("Pushing");
+            let mut auth =
+                git::RemoteAuth::new(&runtime.config.credentials, Some(&mut *runtime.prompter));
+            git::push_remote(&runtime.repo, &runtime.remote, &refspecs, &mut auth)?;
+            report.tags.extend(rls_tags);
+        }
     }
 
     if let Err(err) = cache_repo.stash_apply(0, None) {
         trace!("Stash apply error: {:?}", err);
+        report.warn(format!("Cache repository stash apply error: {}", err));
     }
     Ok(())
 }
+
+/// Returns manifest paths of workspace and its packages.
+fn manifest_paths(workspace: &Workspace) -> Vec<PathBuf> {
+    std::iter::once(workspace.manifest_path())
+        .chain(workspace.packages().iter().map(|pkg| pkg.manifest_path()))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Creates release tags, either workspace version tag
+/// or tags for every released package if workspace has no version.
+pub(crate) fn release_tags(
+    workspace: &Workspace,
+    update: &Update,
+    released: &[(String, semver::Version)],
+) -> Vec<String> {
+    match workspace.version() {
+        Some(version) => vec![format!("refs/tags/v{}", update.bump(version))],
+        None => released
+            .iter()
+            .map(|(name, version)| format!("refs/tags/{}-v{}", name, version))
+            .collect(),
+    }
+}
+
+/// Creates `Released-Packages` commit trailer.
+fn released_trailer(released: &[(String, semver::Version)]) -> String {
+    let packages: Vec<String> = released
+        .iter()
+        .map(|(name, version)| format!("{}@{}", name, version))
+        .collect();
+    format!("Released-Packages: {}", packages.join(", "))
+}
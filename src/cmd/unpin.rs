@@ -0,0 +1,40 @@
+//! Unpin command.
+
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+use crate::updater::{WorkspaceManifest, WorkspaceManifestExt};
+
+use super::exec::ExecRuntime;
+use super::pin_git::{check_downstream, save_manifest, select_packages};
+
+/// Unpin options.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "unpin", about = "Removes git pins of workspace packages.")]
+pub struct Command {
+    /// Packages to unpin, all workspace packages if empty.
+    pub packages: Vec<String>,
+
+    /// Downstream manifest to remove pins from.
+    #[structopt(parse(from_os_str), long = "manifest")]
+    pub manifest: PathBuf,
+
+    /// Saves results to preview file.
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+/// Executes an `unpin` command.
+pub fn execute(cmd: &Command, runtime: ExecRuntime) -> Result<(), failure::Error> {
+    check_downstream(&runtime.workspace, &cmd.manifest)?;
+    let packages = select_packages(&runtime.workspace, &cmd.packages)?;
+    let unpin = |manifest: &mut WorkspaceManifest| {
+        for package in &packages {
+            if manifest.remove_overrides(package, "git") {
+                println!("  * {} unpinned", package.name());
+            }
+        }
+    };
+    save_manifest(&cmd.manifest, unpin, cmd.dry_run)
+}
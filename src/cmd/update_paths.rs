@@ -6,6 +6,7 @@ use failure::ResultExt;
 use structopt::StructOpt;
 
 use crate::{
+    git::{self, Repository},
     parser::collect_members,
     updater::Updater,
     util::{self, CleanPath},
@@ -26,6 +27,18 @@ pub struct Command {
     #[structopt(long = "force-deps", short = "f")]
     pub force_versions: bool,
 
+    /// Replaces path dependencies on members with versions.
+    #[structopt(long = "to-versions", conflicts_with = "force_versions")]
+    pub to_versions: bool,
+
+    /// Writes forced paths to `[patch.<registry>]` instead of `[replace]`.
+    #[structopt(long = "patch")]
+    pub patch: Option<String>,
+
+    /// Initializes and updates nested submodules before opening dependencies.
+    #[structopt(long = "update-submodules")]
+    pub update_submodules: bool,
+
     /// Git submodule dependency workspace.
     #[structopt(parse(from_os_str), long = "dep", short = "d")]
     pub dependencies: Vec<PathBuf>,
@@ -37,32 +50,58 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
         trace!(
             "Package: {} used workspace members: {:?}",
             package.name(),
-            collect_members(&runtime.workspace, package)
+            collect_members(&runtime.workspace, package, &runtime.config.disabled_cfgs)
         );
     }
     let mut updater = Updater::new(&mut runtime.repo)?;
-    updater.set_paths(
-        &mut runtime.repo,
-        &runtime.workspace,
-        cmd.dry_run,
-        cmd.force_versions,
-    )?;
+    updater.patch_registry = cmd.patch.clone();
+    if cmd.to_versions {
+        updater.strip_paths(
+            &mut runtime.repo,
+            &runtime.workspace,
+            &runtime.workspace,
+            cmd.dry_run,
+        )?;
+    } else {
+        updater.set_paths(
+            &mut runtime.repo,
+            &runtime.workspace,
+            cmd.dry_run,
+            cmd.force_versions,
+        )?;
+    }
     util::init::set_cwd(&runtime.directory)?;
 
+    if cmd.update_submodules && !cmd.dependencies.is_empty() {
+        let mut auth =
+            git::RemoteAuth::new(&runtime.config.credentials, Some(&mut *runtime.prompter));
+        runtime.repo.update_submodules(true, true, &mut auth)?;
+    }
     for dep in &cmd.dependencies {
         let dep_dir = std::fs::canonicalize(dep)?.fix_path();
         let config = ws::cargo_config(dep_dir.clone());
         let cargo = ws::cargo_workspace(&config)
             .with_context(|e| format!("Error opening dependency {:?} workspace: {}", dep, e))?;
-        // let mut repo = Repository::open(&dep_dir)?;
-        let workspace = Workspace::new(&cargo, &mut runtime.repo)?;
-        updater.set_submodule_paths(
-            &mut runtime.repo,
-            &runtime.workspace,
-            &workspace,
-            cmd.dry_run,
-            cmd.force_versions,
-        )?;
+        let mut repo = Repository::discover(&dep_dir)
+            .with_context(|e| format!("Error opening dependency {:?} repository: {}", dep, e))?;
+        trace!("Dependency {:?} repository: {:?}", dep, repo.workdir());
+        let workspace = Workspace::new(&cargo, &mut repo)?;
+        if cmd.to_versions {
+            updater.strip_paths(
+                &mut runtime.repo,
+                &runtime.workspace,
+                &workspace,
+                cmd.dry_run,
+            )?;
+        } else {
+            updater.set_submodule_paths(
+                &mut runtime.repo,
+                &runtime.workspace,
+                &workspace,
+                cmd.dry_run,
+                cmd.force_versions,
+            )?;
+        }
     }
 
     updater.workspace.save_preview()?;
@@ -71,6 +110,7 @@ pub fn execute(cmd: &Command, mut runtime: ExecRuntime) -> Result<(), failure::E
             updater.workspace.index_preview_path(),
             runtime.workspace.manifest_path(),
         )?;
+        util::commit::remove_previews(runtime.workspace.manifest_path())?;
     }
     Ok(())
 }
@@ -7,10 +7,42 @@ use git2::{Index, Oid, Repository, Signature};
 
 use crate::util::CleanPath;
 
+use super::Signer;
+
+/// Commit author or committer identity.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+/// Commit identity and trailers configuration.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+pub struct CommitConfig {
+    /// Author overriding git config user.
+    pub author: Option<Identity>,
+    /// Committer overriding git config user.
+    pub committer: Option<Identity>,
+    /// Trailers added to every commit, e.g. `Release-Tool: wtf-rlsr`.
+    pub trailers: Vec<String>,
+    /// Adds `Released-Packages` trailer to release commits.
+    pub released_packages: bool,
+    /// Adds `Signed-off-by` trailer of the committer.
+    pub signoff: bool,
+}
+
 /// Commit builder structure.
 pub struct CommitBuilder {
-    signature: Signature<'static>,
+    author: Signature<'static>,
+    committer: Signature<'static>,
     index: Index,
+    /// Trailers appended to commit message.
+    pub trailers: Vec<String>,
+    /// Adds `Signed-off-by` trailer of the committer.
+    pub signoff: bool,
+    /// Commit signer, commits are not signed if empty.
+    pub signer: Option<Signer>,
 }
 
 impl CommitBuilder {
@@ -22,7 +54,32 @@ impl CommitBuilder {
             signature.email().unwrap()
         );
         let index = repo.index()?;
-        Ok(CommitBuilder { signature, index })
+        Ok(CommitBuilder {
+            author: signature.clone(),
+            committer: signature,
+            index,
+            trailers: Vec::new(),
+            signoff: false,
+            signer: None,
+        })
+    }
+
+    /// Overrides commit identity and adds trailers from configuration.
+    pub fn configure(&mut self, config: &CommitConfig) -> Result<(), Error> {
+        if let Some(author) = &config.author {
+            self.author = Signature::now(&author.name, &author.email)?;
+        }
+        if let Some(committer) = &config.committer {
+            self.committer = Signature::now(&committer.name, &committer.email)?;
+        }
+        trace!(
+            "Commit author: {} committer: {}",
+            self.author,
+            self.committer
+        );
+        self.trailers.extend(config.trailers.iter().cloned());
+        self.signoff = config.signoff;
+        Ok(())
     }
 
     /// Adds file path to git commit.
@@ -33,6 +90,14 @@ impl CommitBuilder {
         Ok(())
     }
 
+    /// Removes file path from git commit.
+    pub fn remove_path<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref().clean_path();
+        trace!("Git rm: {:?}", path);
+        self.index.remove_path(&path)?;
+        Ok(())
+    }
+
     /// Commits changes and sets detached HEAD.
     pub fn commit(&mut self, message: &str, repo: &mut Repository) -> Result<Oid, Error> {
         self.index.write()?;
@@ -41,17 +106,49 @@ impl CommitBuilder {
         let head_oid = repo.head()?.target().unwrap();
         let commit = repo.find_commit(head_oid)?;
         trace!("Creating new commit on prev: {:?}", commit);
+        let message = self.message(message);
+        let message = message.as_str();
         trace!("Commit message: {}", message);
 
-        let new_commit = repo.commit(
-            None,
-            &self.signature,
-            &self.signature,
-            message,
-            &tree,
-            &[&commit],
-        )?;
+        let new_commit = match &self.signer {
+            Some(signer) => {
+                let buffer = repo.commit_create_buffer(
+                    &self.author,
+                    &self.committer,
+                    message,
+                    &tree,
+                    &[&commit],
+                )?;
+                let content = buffer
+                    .as_str()
+                    .ok_or_else(|| format_err!("commit buffer is not valid UTF-8"))?;
+                let signature = signer.sign(content)?;
+                trace!("Creating signed commit");
+                repo.commit_signed(content, &signature, Some("gpgsig"))?
+            }
+            None => repo.commit(
+                None,
+                &self.author,
+                &self.committer,
+                message,
+                &tree,
+                &[&commit],
+            )?,
+        };
         repo.head()?.set_target(new_commit, "")?;
         Ok(new_commit)
     }
+
+    /// Creates commit message with trailers.
+    fn message(&self, message: &str) -> String {
+        let mut trailers = self.trailers.clone();
+        if self.signoff {
+            trailers.push(format!("Signed-off-by: {}", self.committer));
+        }
+        if trailers.is_empty() {
+            message.to_owned()
+        } else {
+            format!("{}\n\n{}", message.trim_end(), trailers.join("\n"))
+        }
+    }
 }
@@ -2,14 +2,48 @@
 
 use std::path::PathBuf;
 
+/// Package change detection mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeDetection {
+    /// Changes in working tree not yet committed.
+    Workdir,
+    /// Changes since last release of a package, committed or not.
+    SinceRelease,
+}
+
+impl Default for ChangeDetection {
+    fn default() -> Self {
+        ChangeDetection::Workdir
+    }
+}
+
+/// Kind of a file change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+/// Changed file in a diff.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileDelta {
+    pub kind: DeltaKind,
+    /// Path of a file after change, path of a deleted file.
+    pub path: PathBuf,
+    /// Path of a renamed file before change.
+    pub old_path: Option<PathBuf>,
+}
+
 /// Git diff structure.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Diff {
     pub files_changed: usize,
     pub insertions: usize,
     pub deletions: usize,
-    pub changed_files: Vec<PathBuf>,
-    pub deleted_files: Vec<PathBuf>,
+    pub deltas: Vec<FileDelta>,
 }
 
 impl Diff {
@@ -20,26 +54,105 @@ impl Diff {
 }
 
 impl From<git2::Diff<'_>> for Diff {
-    fn from(diff: git2::Diff<'_>) -> Diff {
+    fn from(mut diff: git2::Diff<'_>) -> Diff {
+        let mut find_opts = git2::DiffFindOptions::new();
+        find_opts.renames(true).for_untracked(true);
+        if let Err(err) = diff.find_similar(Some(&mut find_opts)) {
+            trace!("Diff rename detection error: {:?}", err);
+        }
         let stats = diff.stats().unwrap();
-        let deleted_files = diff
-            .deltas()
-            .map(|delta| delta.new_file())
-            .filter(|file| file.size() == 0)
-            .filter_map(|file| Some(file.path()?.to_path_buf()))
-            .collect();
-        let changed_files = diff
-            .deltas()
-            .map(|delta| delta.new_file())
-            .filter(|file| file.size() > 0)
-            .filter_map(|file| Some(file.path()?.to_path_buf()))
-            .collect();
+        let deltas = diff.deltas().filter_map(file_delta).collect();
         Diff {
             files_changed: stats.files_changed(),
             insertions: stats.insertions(),
             deletions: stats.deletions(),
-            deleted_files,
-            changed_files,
+            deltas,
+        }
+    }
+}
+
+/// Classifies a git diff delta by its status.
+fn file_delta(delta: git2::DiffDelta<'_>) -> Option<FileDelta> {
+    let new_path = delta.new_file().path().map(|path| path.to_path_buf());
+    let old_path = delta.old_file().path().map(|path| path.to_path_buf());
+    let (kind, path, old_path) = match delta.status() {
+        git2::Delta::Added | git2::Delta::Untracked | git2::Delta::Copied => {
+            (DeltaKind::Added, new_path?, None)
+        }
+        git2::Delta::Modified | git2::Delta::Typechange => (DeltaKind::Modified, new_path?, None),
+        git2::Delta::Deleted => (DeltaKind::Deleted, old_path?, None),
+        git2::Delta::Renamed => (DeltaKind::Renamed, new_path?, old_path),
+        status => {
+            trace!("Skipping diff delta {:?}: {:?}", status, new_path);
+            return None;
+        }
+    };
+    Some(FileDelta {
+        kind,
+        path,
+        old_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use git2::{DiffOptions, Repository};
+
+    #[test]
+    fn delta_kinds() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let mut index = repo.index().unwrap();
+        for name in &["modified.txt", "deleted.txt", "renamed.txt"] {
+            // Distinct contents, so renamed file is the only rename candidate
+            let contents: String = (1..=4).map(|i| format!("{} line {}\n", name, i)).collect();
+            std::fs::write(dir.path().join(name), contents).unwrap();
+            index.add_path(Path::new(name)).unwrap();
         }
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        std::fs::write(dir.path().join("modified.txt"), "changed\n").unwrap();
+        std::fs::remove_file(dir.path().join("deleted.txt")).unwrap();
+        std::fs::rename(dir.path().join("renamed.txt"), dir.path().join("moved.txt")).unwrap();
+        std::fs::write(dir.path().join("staged.txt"), "staged\n").unwrap();
+        std::fs::write(dir.path().join("untracked.txt"), "untracked\n").unwrap();
+        index.add_path(Path::new("staged.txt")).unwrap();
+        index.write().unwrap();
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true);
+        let diff: Diff = repo
+            .diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))
+            .unwrap()
+            .into();
+        let mut deltas = diff.deltas.clone();
+        deltas.sort_by(|a, b| a.path.cmp(&b.path));
+        let delta = |kind, path: &str, old_path: Option<&str>| FileDelta {
+            kind,
+            path: PathBuf::from(path),
+            old_path: old_path.map(PathBuf::from),
+        };
+        assert_eq!(
+            deltas,
+            vec![
+                delta(DeltaKind::Deleted, "deleted.txt", None),
+                delta(DeltaKind::Modified, "modified.txt", None),
+                delta(DeltaKind::Renamed, "moved.txt", Some("renamed.txt")),
+                delta(DeltaKind::Added, "staged.txt", None),
+                delta(DeltaKind::Added, "untracked.txt", None),
+            ]
+        );
+        assert_eq!(diff.files_changed, 5);
+        assert!(!diff.is_empty());
     }
 }
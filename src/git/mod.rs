@@ -1,15 +1,19 @@
 //! Git wrappers and helpers module.
 
 mod commit;
+mod credentials;
 mod diff;
 mod options;
 mod repository;
+mod sign;
 mod util;
 
 pub use self::commit::*;
+pub use self::credentials::*;
 pub use self::diff::*;
 pub(crate) use self::options::*;
 pub use self::repository::*;
+pub use self::sign::*;
 pub use self::util::*;
 
 use std::path::Path;
@@ -28,10 +32,12 @@ pub fn init_cache_repo<P: AsRef<Path>, Q: AsRef<Path>>(
     path: P,
     source: Q,
     remote_branch: &str,
+    strategy: MergeStrategy,
+    auth: &mut RemoteAuth,
 ) -> Result<Repository, git2::Error> {
     if !path.as_ref().exists() {
         trace!("Cloning repository to {:?}", path.as_ref());
-        let repo = Repository::clone_recurse(source.as_ref().to_str().unwrap(), path)?;
+        let repo = Repository::clone_recurse(source.as_ref().to_str().unwrap(), path, auth)?;
         Ok(repo)
     } else {
         let mut repo = Repository::open(path.as_ref())?;
@@ -44,7 +50,13 @@ pub fn init_cache_repo<P: AsRef<Path>, Q: AsRef<Path>>(
         ) {
             trace!("Stash error: {:?}", err);
         }
-        pull_remote(&repo, &remote_branch, &mut repo.find_remote("origin")?)?;
+        pull_remote(
+            &repo,
+            &remote_branch,
+            &mut repo.find_remote("origin")?,
+            strategy,
+            auth,
+        )?;
         Ok(repo)
     }
 }
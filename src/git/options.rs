@@ -1,7 +1,9 @@
-use git2::{Cred, FetchOptions, PushOptions, RemoteCallbacks};
+use git2::{FetchOptions, PushOptions, RemoteCallbacks};
+
+use super::{CredentialsChain, RemoteAuth};
 
 /// Default git remote callbacks.
-pub(crate) fn default_remote_callbacks() -> RemoteCallbacks<'static> {
+pub(crate) fn default_remote_callbacks(auth: RemoteAuth<'_>) -> RemoteCallbacks<'_> {
     let mut remote_callbacks = RemoteCallbacks::new();
     remote_callbacks.transfer_progress(|progress| {
         if progress.received_objects() % 1000 == 0 {
@@ -13,20 +15,17 @@ pub(crate) fn default_remote_callbacks() -> RemoteCallbacks<'static> {
         }
         true
     });
-    let homedir = dirs::home_dir().unwrap();
-    let ssh_pub = homedir.join(".ssh").join("id_rsa.pub");
-    let ssh_priv = homedir.join(".ssh").join("id_rsa");
-    trace!("SSH Public key location: {:?}", ssh_pub);
-    trace!("SSH Private key location: {:?}", ssh_priv);
-    remote_callbacks
-        .credentials(move |_, _, _| Cred::ssh_key("git", Some(&ssh_pub), &ssh_priv, None));
+    let mut credentials = CredentialsChain::new(auth);
+    remote_callbacks.credentials(move |url, username_from_url, allowed| {
+        credentials.next(url, username_from_url, allowed)
+    });
     remote_callbacks
 }
 
 /// Default git push options.
-pub(crate) fn default_push_options() -> PushOptions<'static> {
+pub(crate) fn default_push_options(auth: RemoteAuth<'_>) -> PushOptions<'_> {
     let mut push_opts = PushOptions::new();
-    push_opts.remote_callbacks(default_remote_callbacks());
+    push_opts.remote_callbacks(default_remote_callbacks(auth));
     // if set to 0 the packbuilder will auto-detect the number of
     // threads to create and the default value is 1.
     push_opts.packbuilder_parallelism(0);
@@ -34,8 +33,8 @@ pub(crate) fn default_push_options() -> PushOptions<'static> {
 }
 
 /// Default git fetch options.
-pub(crate) fn default_fetch_options() -> FetchOptions<'static> {
+pub(crate) fn default_fetch_options(auth: RemoteAuth<'_>) -> FetchOptions<'_> {
     let mut fetch_opts = FetchOptions::new();
-    fetch_opts.remote_callbacks(default_remote_callbacks());
+    fetch_opts.remote_callbacks(default_remote_callbacks(auth));
     fetch_opts
 }
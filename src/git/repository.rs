@@ -10,12 +10,17 @@ use git2::{DiffOptions, Error, Repository as GitRepository};
 
 use crate::util::{source_git_diff_paths, CleanPath};
 
-use super::{default_fetch_options, Diff};
+use super::{default_fetch_options, ChangeDetection, Diff, RemoteAuth};
 
 /// Workspace Git repository structure.
 pub struct Repository {
     inner: GitRepository,
     cache: HashMap<String, Diff>,
+    detection: ChangeDetection,
+    /// Workspace version, its tag marks release of every package.
+    workspace_version: Option<semver::Version>,
+    /// Last commits which changed package versions.
+    releases: HashMap<String, Option<git2::Oid>>,
 }
 
 impl Repository {
@@ -23,15 +28,44 @@ impl Repository {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let inner = GitRepository::open(path)?;
         let cache = HashMap::default();
-        Ok(Repository { inner, cache })
+        Ok(Repository {
+            inner,
+            cache,
+            detection: ChangeDetection::default(),
+            workspace_version: None,
+            releases: HashMap::default(),
+        })
+    }
+
+    /// Opens repository containing `path`, looking up parent directories.
+    pub fn discover<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let inner = GitRepository::discover(path)?;
+        let cache = HashMap::default();
+        Ok(Repository {
+            inner,
+            cache,
+            detection: ChangeDetection::default(),
+            workspace_version: None,
+            releases: HashMap::default(),
+        })
     }
 
     /// Attempt to clone repository recursively to `dest`.
-    pub fn clone_recurse<P: AsRef<Path>>(source: &str, dest: P) -> Result<Self, Error> {
+    pub fn clone_recurse<P: AsRef<Path>>(
+        source: &str,
+        dest: P,
+        auth: &mut RemoteAuth,
+    ) -> Result<Self, Error> {
         let inner = GitRepository::clone(source, dest)?;
         let cache = HashMap::default();
-        let repo = Repository { inner, cache };
-        repo.update_submodules(true, false)?;
+        let repo = Repository {
+            inner,
+            cache,
+            detection: ChangeDetection::default(),
+            workspace_version: None,
+            releases: HashMap::default(),
+        };
+        repo.update_submodules(true, false, auth)?;
         Ok(repo)
     }
 
@@ -46,25 +80,136 @@ impl Repository {
         self.head_commit()?.tree()
     }
 
+    /// Sets package change detection mode.
+    pub fn set_change_detection(&mut self, detection: ChangeDetection) {
+        self.detection = detection;
+    }
+
+    /// Sets workspace version used to look up workspace release tags.
+    pub fn set_workspace_version(&mut self, version: Option<semver::Version>) {
+        self.workspace_version = version;
+    }
+
     /// Creates a diff for a package.
     pub fn diff(&mut self, pkg: &Package) -> Result<&Diff, Error> {
         let pkg_name = pkg.name().to_string();
         if !self.cache.contains_key(&pkg_name) {
+            self.find_release_commits(&[pkg])?;
             let mut diff_opts = DiffOptions::new();
             diff_opts.include_untracked(true);
             diff_opts.recurse_untracked_dirs(true);
-            for path in source_git_diff_paths(pkg) {
-                trace!("Diff path: {}", self.rel_path(&path).clean_path().display());
-                diff_opts.pathspec(self.rel_path(&path).clean_path());
+            for pathspec in source_git_diff_paths(pkg, |path| self.rel_path(path)) {
+                trace!("Diff pathspec: {}", pathspec);
+                diff_opts.pathspec(pathspec);
             }
-            let diff = self
-                .diff_index_to_workdir(None, Some(&mut diff_opts))?
-                .into();
+            let diff = self.package_diff(pkg, &mut diff_opts)?;
             self.cache.insert(pkg_name.to_owned(), diff);
         }
         Ok(self.cache.get(&pkg_name).unwrap())
     }
 
+    /// Creates a diff of package paths according to change detection mode.
+    fn package_diff(&self, pkg: &Package, diff_opts: &mut DiffOptions) -> Result<Diff, Error> {
+        let base = match self.detection {
+            ChangeDetection::Workdir => None,
+            ChangeDetection::SinceRelease => self.release_commit(pkg)?,
+        };
+        let diff = match base {
+            Some(base) => {
+                trace!("Package {} last release commit: {}", pkg.name(), base.id());
+                let tree = base.tree()?;
+                self.diff_tree_to_workdir_with_index(Some(&tree), Some(diff_opts))?
+            }
+            None => self.diff_index_to_workdir(None, Some(diff_opts))?,
+        };
+        Ok(diff.into())
+    }
+
+    /// Finds commit of the last package release.
+    /// Looks up release tags first, then the last commit which changed package version.
+    fn release_commit(&self, pkg: &Package) -> Result<Option<git2::Commit<'_>>, Error> {
+        for tag in self.release_tags(pkg) {
+            if let Ok(reference) = self.find_reference(&tag) {
+                return Ok(Some(reference.peel_to_commit()?));
+            }
+        }
+        match self.releases.get(pkg.name().as_str()) {
+            Some(Some(oid)) => Ok(Some(self.find_commit(*oid)?)),
+            _ => {
+                trace!("No release of package {} found", pkg.name());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns tags marking a package release.
+    fn release_tags(&self, pkg: &Package) -> Vec<String> {
+        let mut tags = vec![format!("refs/tags/{}-v{}", pkg.name(), pkg.version())];
+        if let Some(version) = &self.workspace_version {
+            tags.push(format!("refs/tags/v{}", version));
+        }
+        tags
+    }
+
+    /// Finds last commits which changed versions of packages without release tags.
+    /// History is walked once for all packages and results are cached.
+    pub fn find_release_commits(&mut self, pkgs: &[&Package]) -> Result<(), Error> {
+        if self.detection != ChangeDetection::SinceRelease {
+            return Ok(());
+        }
+        let mut pending: Vec<(String, PathBuf, String)> = pkgs
+            .iter()
+            .filter(|pkg| !self.releases.contains_key(pkg.name().as_str()))
+            .filter(|pkg| {
+                let tags = self.release_tags(pkg);
+                !tags.iter().any(|tag| self.find_reference(tag).is_ok())
+            })
+            .map(|pkg| {
+                let manifest_path = self.rel_path(pkg.manifest_path()).clean_path();
+                (
+                    pkg.name().to_string(),
+                    manifest_path,
+                    pkg.version().to_string(),
+                )
+            })
+            .collect();
+        let mut found = Vec::new();
+        if !pending.is_empty() {
+            let repo = &self.inner;
+            let mut revwalk = repo.revwalk()?;
+            revwalk.push_head()?;
+            for oid in revwalk {
+                let commit = repo.find_commit(oid?)?;
+                let parent = match commit.parents().next() {
+                    Some(parent) => parent,
+                    None => break,
+                };
+                let (tree, parent_tree) = (commit.tree()?, parent.tree()?);
+                pending.retain(|(name, manifest_path, version)| {
+                    let entry_id =
+                        |tree: &git2::Tree| tree.get_path(manifest_path).ok().map(|e| e.id());
+                    if entry_id(&tree) == entry_id(&parent_tree) {
+                        return true;
+                    }
+                    let current = manifest_version(repo, &tree, manifest_path);
+                    let previous = manifest_version(repo, &parent_tree, manifest_path);
+                    if current.as_ref() == Some(version) && previous != current {
+                        found.push((name.clone(), Some(commit.id())));
+                        false
+                    } else {
+                        true
+                    }
+                });
+                if pending.is_empty() {
+                    break;
+                }
+            }
+        }
+        found.extend(pending.into_iter().map(|(name, _, _)| (name, None)));
+        self.releases.extend(found);
+        Ok(())
+    }
+
     /// Gets cached diff for a package.
     pub fn cached_diff(&mut self, name: &str) -> Option<&Diff> {
         self.cache.get(name)
@@ -83,21 +228,33 @@ impl Repository {
         let path = self.rel_path(path).clean_path();
         let entry = tree.get_path(&path)?;
         let entry_object = entry.to_object(&self)?;
-        let entry_blob = entry_object.into_blob().unwrap();
+        let entry_blob = entry_object.into_blob().map_err(|object| {
+            Error::from_str(&format!(
+                "{} is a {} and not a file",
+                path.display(),
+                object.kind().map(|kind| kind.str()).unwrap_or("object")
+            ))
+        })?;
         Ok(entry_blob.content().to_owned())
     }
 
     /// Update submodules recursively.
-    pub fn update_submodules(&self, init: bool, deep: bool) -> Result<(), Error> {
+    pub fn update_submodules(
+        &self,
+        init: bool,
+        deep: bool,
+        auth: &mut RemoteAuth,
+    ) -> Result<(), Error> {
         fn add_subrepos(
             repo: &git2::Repository,
             list: &mut Vec<git2::Repository>,
             init: bool,
+            auth: &mut RemoteAuth,
         ) -> Result<(), Error> {
             for mut subm in repo.submodules()? {
                 let mut opts = git2::SubmoduleUpdateOptions::new();
                 trace!("Fetching submodule {:?}", subm.url().unwrap());
-                opts.fetch(default_fetch_options());
+                opts.fetch(default_fetch_options(auth.reborrow()));
                 subm.update(init, Some(&mut opts))?;
                 list.push(subm.open()?);
             }
@@ -105,16 +262,33 @@ impl Repository {
         }
 
         let mut repos = Vec::new();
-        add_subrepos(self, &mut repos, init)?;
+        add_subrepos(self, &mut repos, init, auth)?;
         if deep {
             while let Some(repo) = repos.pop() {
-                add_subrepos(&repo, &mut repos, init)?;
+                add_subrepos(&repo, &mut repos, init, auth)?;
             }
         }
         Ok(())
     }
 }
 
+/// Reads package version from a manifest in a tree.
+fn manifest_version(repo: &GitRepository, tree: &git2::Tree, path: &Path) -> Option<String> {
+    let blob = tree
+        .get_path(path)
+        .ok()?
+        .to_object(repo)
+        .ok()?
+        .peel_to_blob()
+        .ok()?;
+    let manifest: toml::Value = toml::from_slice(blob.content()).ok()?;
+    manifest
+        .get("package")?
+        .get("version")?
+        .as_str()
+        .map(|version| version.to_owned())
+}
+
 impl Deref for Repository {
     type Target = GitRepository;
 
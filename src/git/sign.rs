@@ -0,0 +1,321 @@
+//! Commit signing module.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use failure::{Error, ResultExt};
+use git2::Repository;
+use tempfile::NamedTempFile;
+
+/// Signature format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SigningFormat {
+    /// GPG signature created with `gpg`.
+    Openpgp,
+    /// SSH signature created with `ssh-keygen -Y sign`.
+    Ssh,
+}
+
+/// Commit signing configuration overriding git config.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+pub struct SigningConfig {
+    /// Signs commits, overrides `commit.gpgsign`.
+    pub sign: Option<bool>,
+    /// Signature format, overrides `gpg.format`.
+    pub format: Option<SigningFormat>,
+    /// Signing key, overrides `user.signingkey`.
+    pub key: Option<String>,
+    /// Signing program, overrides `gpg.program` and `gpg.ssh.program`.
+    pub program: Option<String>,
+}
+
+/// Commit signer.
+#[derive(Debug, Clone)]
+pub struct Signer {
+    format: SigningFormat,
+    key: Option<String>,
+    program: String,
+}
+
+impl Signer {
+    /// Creates a signer from git config and wtf-rlsr overrides.
+    /// Returns `None` if commits are not signed.
+    pub fn from_config(repo: &Repository, config: &SigningConfig) -> Result<Option<Self>, Error> {
+        let git_config = repo.config()?;
+        let sign = match config.sign {
+            Some(sign) => sign,
+            None => git_config.get_bool("commit.gpgsign").unwrap_or(false),
+        };
+        if !sign {
+            return Ok(None);
+        }
+        let format = match config.format {
+            Some(format) => format,
+            None => match git_config.get_string("gpg.format") {
+                Ok(ref format) if format == "ssh" => SigningFormat::Ssh,
+                Ok(ref format) if format == "openpgp" => SigningFormat::Openpgp,
+                Ok(format) => return Err(format_err!("unsupported gpg.format {:?}", format)),
+                Err(_) => SigningFormat::Openpgp,
+            },
+        };
+        let key = config
+            .key
+            .clone()
+            .or_else(|| git_config.get_string("user.signingkey").ok());
+        let program = match (&config.program, format) {
+            (Some(program), _) => program.clone(),
+            (None, SigningFormat::Openpgp) => git_config
+                .get_string("gpg.program")
+                .unwrap_or_else(|_| "gpg".to_owned()),
+            (None, SigningFormat::Ssh) => git_config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_owned()),
+        };
+        if format == SigningFormat::Ssh && key.is_none() {
+            return Err(format_err!("ssh commit signing requires user.signingkey"));
+        }
+        trace!("Commit signer: {:?} {:?} ({})", format, key, program);
+        Ok(Some(Signer {
+            format,
+            key,
+            program,
+        }))
+    }
+
+    /// Signs commit buffer and returns armored signature.
+    pub fn sign(&self, buffer: &str) -> Result<String, Error> {
+        let mut command = Command::new(&self.program);
+        // Literal ssh key file has to outlive signing command
+        let key_file;
+        match self.format {
+            SigningFormat::Openpgp => {
+                command.args(&["--status-fd=2", "-bsa"]);
+                if let Some(key) = &self.key {
+                    command.args(&["-u", key]);
+                }
+            }
+            SigningFormat::Ssh => {
+                key_file = ssh_key(self.key.as_ref().unwrap())?;
+                command
+                    .args(&["-Y", "sign", "-n", "git", "-f"])
+                    .arg(key_file.path());
+            }
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|e| format!("error starting {}: {}", self.program, e))?;
+        child.stdin.take().unwrap().write_all(buffer.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(format_err!(
+                "{} failed to sign commit: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+/// Ssh signing key file.
+enum SshKey {
+    /// Key file path.
+    Path(PathBuf),
+    /// Literal `key::` public key written to a temporary file,
+    /// removed when dropped.
+    Literal(NamedTempFile),
+}
+
+impl SshKey {
+    /// Returns path of the key file.
+    fn path(&self) -> &Path {
+        match self {
+            SshKey::Path(path) => path,
+            SshKey::Literal(file) => file.path(),
+        }
+    }
+}
+
+/// Returns ssh signing key file.
+fn ssh_key(key: &str) -> Result<SshKey, Error> {
+    if let Some(literal) = key.strip_prefix("key::") {
+        let mut file = tempfile::Builder::new()
+            .prefix("wtf-rlsr-signing-")
+            .suffix(".pub")
+            .tempfile()?;
+        writeln!(file, "{}", literal.trim())?;
+        return Ok(SshKey::Literal(file));
+    }
+    match key.strip_prefix("~/") {
+        Some(rest) => Ok(SshKey::Path(
+            dirs::home_dir()
+                .ok_or_else(|| format_err!("home directory not found"))?
+                .join(rest),
+        )),
+        None => Ok(SshKey::Path(PathBuf::from(key))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::git::CommitBuilder;
+
+    /// Creates a repository with an initial commit in a temporary directory.
+    fn init_repo(dir: &Path) -> Repository {
+        let repo = Repository::init(dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        std::fs::write(dir.join("README"), "test\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("README")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        drop(tree);
+        repo
+    }
+
+    /// Returns false if a program can't be started.
+    fn available(program: &str) -> bool {
+        match Command::new(program).arg("--version").output() {
+            Ok(_) => true,
+            Err(_) => {
+                eprintln!("{} not found, skipping test", program);
+                false
+            }
+        }
+    }
+
+    /// Runs a command and asserts it succeeded.
+    fn run(command: &mut Command) {
+        let output = command.output().unwrap();
+        assert!(
+            output.status.success(),
+            "{:?}: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// Commits a change with a signer, writes commit signature
+    /// and signed data to `commit.sig` and `commit` files in a directory.
+    fn signed_commit(dir: &Path, config: &SigningConfig) -> (PathBuf, PathBuf) {
+        let workdir = dir.join("repo");
+        let mut repo = init_repo(&workdir);
+        let signer = Signer::from_config(&repo, config).unwrap().unwrap();
+
+        std::fs::write(workdir.join("README"), "signed\n").unwrap();
+        let mut commit = CommitBuilder::new(&repo).unwrap();
+        commit.signer = Some(signer);
+        commit.add_path("README").unwrap();
+        let oid = commit.commit("signed", &mut repo).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+
+        let (signature, data) = repo.extract_signature(&oid, None).unwrap();
+        let signature_path = dir.join("commit.sig");
+        let data_path = dir.join("commit");
+        std::fs::write(&signature_path, &*signature).unwrap();
+        std::fs::write(&data_path, &*data).unwrap();
+        (signature_path, data_path)
+    }
+
+    #[test]
+    fn ssh_signed_commit() {
+        if !available("ssh-keygen") {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let key = dir.path().join("key");
+        run(Command::new("ssh-keygen")
+            .args(&["-q", "-t", "ed25519", "-N", "", "-C", "test", "-f"])
+            .arg(&key));
+        let config = SigningConfig {
+            sign: Some(true),
+            format: Some(SigningFormat::Ssh),
+            key: Some(key.to_str().unwrap().to_owned()),
+            program: None,
+        };
+        let (signature, data) = signed_commit(dir.path(), &config);
+        let public_key = std::fs::read_to_string(key.with_extension("pub")).unwrap();
+        let allowed = dir.path().join("allowed_signers");
+        std::fs::write(
+            &allowed,
+            format!("test@example.com namespaces=\"git\" {}", public_key),
+        )
+        .unwrap();
+        run(Command::new("ssh-keygen")
+            .args(&["-Y", "verify", "-I", "test@example.com", "-n", "git", "-f"])
+            .arg(&allowed)
+            .arg("-s")
+            .arg(&signature)
+            .stdin(std::fs::File::open(&data).unwrap()));
+    }
+
+    #[test]
+    fn gpg_signed_commit() {
+        if !available("gpg") {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path().join("gnupg");
+        std::fs::create_dir(&home).unwrap();
+        run(Command::new("gpg")
+            .arg("--homedir")
+            .arg(&home)
+            .args(&["--batch", "--pinentry-mode", "loopback", "--passphrase", ""])
+            .args(&["--quick-generate-key", "Test <test@example.com>"])
+            .args(&["default", "sign", "never"]));
+        // Signer runs a program without extra arguments
+        let program = dir.path().join("gpg-test");
+        std::fs::write(
+            &program,
+            format!(
+                "#!/bin/sh\nexec gpg --batch --homedir {:?} \"$@\"\n",
+                home.to_str().unwrap()
+            ),
+        )
+        .unwrap();
+        run(Command::new("chmod").arg("+x").arg(&program));
+        let config = SigningConfig {
+            sign: Some(true),
+            format: Some(SigningFormat::Openpgp),
+            key: Some("test@example.com".to_owned()),
+            program: Some(program.to_str().unwrap().to_owned()),
+        };
+        let (signature, data) = signed_commit(dir.path(), &config);
+        run(Command::new(&program)
+            .arg("--verify")
+            .arg(&signature)
+            .arg(&data));
+        let _ = Command::new("gpgconf")
+            .arg("--homedir")
+            .arg(&home)
+            .args(&["--kill", "gpg-agent"])
+            .status();
+    }
+
+    #[test]
+    fn ssh_literal_key_file() {
+        let key = ssh_key("key::ssh-ed25519 AAAA test ").unwrap();
+        let path = key.path().to_path_buf();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "ssh-ed25519 AAAA test\n"
+        );
+        drop(key);
+        assert!(!path.exists());
+
+        let key = ssh_key("/keys/id_ed25519").unwrap();
+        assert_eq!(key.path(), Path::new("/keys/id_ed25519"));
+    }
+}
@@ -1,14 +1,114 @@
+use std::str::FromStr;
+
 use git2::{
-    build::CheckoutBuilder, AnnotatedCommit, AutotagOption, Error, FetchOptions, Reference, Remote,
-    Repository,
+    build::CheckoutBuilder, AnnotatedCommit, AutotagOption, Direction, Error, FileFavor, Index,
+    MergeOptions, Oid, Reference, Remote, Repository, ResetType,
 };
 
-use super::default_push_options;
+use super::{default_fetch_options, default_push_options, default_remote_callbacks, RemoteAuth};
+
+/// Strategy used when pulled commits conflict with local commits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Leaves repository untouched and fails with conflicting paths.
+    Abort,
+    /// Resets local branch to the remote commit.
+    Reset,
+    /// Resolves conflicts in favor of local changes.
+    Ours,
+}
+
+impl Default for MergeStrategy {
+    fn default() -> Self {
+        MergeStrategy::Abort
+    }
+}
+
+impl FromStr for MergeStrategy {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(MergeStrategy::Abort),
+            "reset" => Ok(MergeStrategy::Reset),
+            "ours" => Ok(MergeStrategy::Ours),
+            _ => Err(format_err!("unknown merge strategy: {}", s)),
+        }
+    }
+}
+
+/// State of a remote branch compared to local branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteBranchState {
+    /// Remote branch doesn't exist.
+    Missing,
+    /// Remote branch is at local branch or behind it.
+    UpToDate,
+    /// Remote branch has commits missing in local branch.
+    Diverged { ahead: usize, behind: usize },
+}
+
+/// Fetches remote branch and compares it with local branch.
+pub fn check_remote_branch(
+    repo: &Repository,
+    remote: &str,
+    branch: &str,
+    auth: &mut RemoteAuth,
+) -> Result<RemoteBranchState, Error> {
+    let mut remote_ref = repo.find_remote(remote)?;
+    let tracking = format!("refs/remotes/{}/{}", remote, branch);
+    let refspec = format!("+refs/heads/{}:{}", branch, tracking);
+    trace!("Fetching {} from {}", refspec, remote);
+    let mut fo = default_fetch_options(auth.reborrow());
+    fo.download_tags(AutotagOption::None);
+    remote_ref.fetch(&[&refspec], Some(&mut fo), None)?;
+    let remote_oid = match repo.refname_to_id(&tracking) {
+        Ok(oid) => oid,
+        Err(_) => return Ok(RemoteBranchState::Missing),
+    };
+    let local_oid = repo.refname_to_id(&format!("refs/heads/{}", branch))?;
+    let (ahead, behind) = repo.graph_ahead_behind(local_oid, remote_oid)?;
+    trace!(
+        "Branch {} is {} commits ahead and {} behind {}",
+        branch,
+        ahead,
+        behind,
+        tracking
+    );
+    if behind == 0 {
+        Ok(RemoteBranchState::UpToDate)
+    } else {
+        Ok(RemoteBranchState::Diverged { ahead, behind })
+    }
+}
+
+/// Lists references on a remote.
+pub fn list_remote_refs(
+    repo: &Repository,
+    remote: &str,
+    auth: &mut RemoteAuth,
+) -> Result<Vec<(String, Oid)>, Error> {
+    let mut remote = repo.find_remote(remote)?;
+    let callbacks = default_remote_callbacks(auth.reborrow());
+    let connection = remote.connect_auth(Direction::Fetch, Some(callbacks), None)?;
+    let refs = connection
+        .list()?
+        .iter()
+        .map(|head| (head.name().to_owned(), head.oid()))
+        .collect();
+    Ok(refs)
+}
 
 /// Attempts to do `git push` to a remote.
-pub fn push_remote(repo: &Repository, remote: &str, refspecs: &[&str]) -> Result<(), Error> {
+pub fn push_remote(
+    repo: &Repository,
+    remote: &str,
+    refspecs: &[&str],
+    auth: &mut RemoteAuth,
+) -> Result<(), Error> {
     let mut remote = repo.find_remote(remote)?;
-    let mut push_opts = default_push_options();
+    let mut push_opts = default_push_options(auth.reborrow());
     remote.push(refspecs, Some(&mut push_opts))
 }
 
@@ -17,17 +117,20 @@ pub fn pull_remote(
     repo: &Repository,
     remote_branch: &str,
     remote: &mut Remote,
+    strategy: MergeStrategy,
+    auth: &mut RemoteAuth,
 ) -> Result<(), Error> {
-    let fetch_commit = fetch_remote(&repo, remote_branch, remote)?;
-    merge_commit(&repo, remote_branch, fetch_commit)
+    let fetch_commit = fetch_remote(&repo, remote_branch, remote, auth)?;
+    merge_commit(&repo, remote_branch, fetch_commit, strategy)
 }
 
 fn fetch_remote<'a>(
     repo: &'a Repository,
     remote_branch: &str,
     remote: &mut Remote,
+    auth: &mut RemoteAuth,
 ) -> Result<AnnotatedCommit<'a>, Error> {
-    let mut fo = FetchOptions::new();
+    let mut fo = default_fetch_options(auth.reborrow());
     // Always fetch all tags.
     // Perform a download and also update tips
     fo.download_tags(AutotagOption::Auto);
@@ -79,22 +182,65 @@ fn fast_forward(repo: &Repository, lb: &mut Reference, rc: &AnnotatedCommit) ->
     Ok(())
 }
 
+/// Resets current branch and working tree to a commit.
+fn reset_to(repo: &Repository, commit: &AnnotatedCommit) -> Result<(), Error> {
+    trace!("Resetting to {}", commit.id());
+    let object = repo.find_object(commit.id(), None)?;
+    repo.reset(&object, ResetType::Hard, None)
+}
+
+/// Returns sorted paths of conflicting index entries.
+fn conflict_paths(idx: &Index) -> Result<Vec<String>, Error> {
+    let mut paths = Vec::new();
+    for conflict in idx.conflicts()? {
+        let conflict = conflict?;
+        let entry = conflict
+            .our
+            .as_ref()
+            .or_else(|| conflict.their.as_ref())
+            .or_else(|| conflict.ancestor.as_ref());
+        if let Some(entry) = entry {
+            paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
 fn normal_merge(
     repo: &Repository,
     local: &AnnotatedCommit,
     remote: &AnnotatedCommit,
+    strategy: MergeStrategy,
 ) -> Result<(), Error> {
     let local_tree = repo.find_commit(local.id())?.tree()?;
     let remote_tree = repo.find_commit(remote.id())?.tree()?;
-    let ancestor = repo
-        .find_commit(repo.merge_base(local.id(), remote.id())?)?
-        .tree()?;
-    let mut idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, None)?;
+    let base = match repo.merge_base(local.id(), remote.id()) {
+        Ok(base) => base,
+        // Unrelated histories can't be merged
+        Err(_) if strategy == MergeStrategy::Reset => return reset_to(repo, remote),
+        Err(err) => return Err(err),
+    };
+    let ancestor = repo.find_commit(base)?.tree()?;
+    let mut opts = MergeOptions::new();
+    if strategy == MergeStrategy::Ours {
+        opts.file_favor(FileFavor::Ours);
+    }
+    let idx = repo.merge_trees(&ancestor, &local_tree, &remote_tree, Some(&opts))?;
 
     if idx.has_conflicts() {
-        trace!("Merge conficts detected...");
-        repo.checkout_index(Some(&mut idx), None)?;
-        return Ok(());
+        let paths = conflict_paths(&idx)?;
+        trace!("Merge conflicts detected: {:?}", paths);
+        if strategy == MergeStrategy::Reset {
+            return reset_to(repo, remote);
+        }
+        return Err(Error::from_str(&format!(
+            "merge of {} into {} conflicts in: {}",
+            remote.id(),
+            local.id(),
+            paths.join(", ")
+        )));
     }
     let result_tree = repo.find_tree(idx.write_tree_to(repo)?)?;
     // now create the merge commit
@@ -120,12 +266,15 @@ fn merge_commit(
     repo: &Repository,
     remote_branch: &str,
     fetch_commit: AnnotatedCommit<'_>,
+    strategy: MergeStrategy,
 ) -> Result<(), Error> {
     // 1. do a merge analysis
     let analysis = repo.merge_analysis(&[&fetch_commit])?;
 
     // 2. Do the appopriate merge
-    if analysis.0.is_fast_forward() {
+    if analysis.0.is_up_to_date() {
+        trace!("Nothing to do...");
+    } else if analysis.0.is_fast_forward() {
         trace!("Doing a fast forward");
         // do a fast forward
         let refname = format!("refs/heads/{}", remote_branch);
@@ -155,9 +304,195 @@ fn merge_commit(
     } else if analysis.0.is_normal() {
         // do a normal merge
         let head_commit = repo.reference_to_annotated_commit(&repo.head()?)?;
-        normal_merge(&repo, &head_commit, &fetch_commit)?;
+        normal_merge(&repo, &head_commit, &fetch_commit, strategy)?;
+    } else if strategy == MergeStrategy::Reset {
+        reset_to(repo, &fetch_commit)?;
     } else {
-        trace!("Nothing to do...");
+        return Err(Error::from_str(&format!(
+            "cannot merge {} into {}: branch is neither fast-forward nor mergeable ({:?})",
+            fetch_commit.id(),
+            remote_branch,
+            analysis.0
+        )));
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::git::CredentialsConfig;
+
+    /// Creates a repository with an initial commit in a directory.
+    fn init_repo(dir: &Path) -> Repository {
+        let repo = Repository::init(dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let head = commit(&repo, Some("HEAD"), None, "README", "base\n");
+        assert_eq!(repo.head().unwrap().target(), Some(head));
+        checkout(&repo);
+        repo
+    }
+
+    /// Creates a commit writing a file over parent tree.
+    fn commit(
+        repo: &Repository,
+        refname: Option<&str>,
+        parent: Option<Oid>,
+        path: &str,
+        contents: &str,
+    ) -> Oid {
+        let parent = parent.map(|oid| repo.find_commit(oid).unwrap());
+        let tree = parent.as_ref().map(|commit| commit.tree().unwrap());
+        let mut builder = repo.treebuilder(tree.as_ref()).unwrap();
+        let blob = repo.blob(contents.as_bytes()).unwrap();
+        builder.insert(path, blob, 0o100_644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let sig = repo.signature().unwrap();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(refname, &sig, &sig, path, &tree, &parents)
+            .unwrap()
+    }
+
+    /// Forces working tree to match HEAD.
+    fn checkout(repo: &Repository) {
+        repo.checkout_head(Some(CheckoutBuilder::new().force()))
+            .unwrap();
+    }
+
+    fn head(repo: &Repository) -> Oid {
+        repo.head().unwrap().target().unwrap()
+    }
+
+    fn branch(repo: &Repository) -> String {
+        repo.head().unwrap().shorthand().unwrap().to_owned()
+    }
+
+    fn read(repo: &Repository, path: &str) -> String {
+        std::fs::read_to_string(repo.workdir().unwrap().join(path)).unwrap()
+    }
+
+    /// Commits a local change of a file and returns a remote commit
+    /// changing a file on top of the initial commit.
+    fn diverged(repo: &Repository, local: (&str, &str), remote: (&str, &str)) -> Oid {
+        let base = head(repo);
+        commit(repo, Some("HEAD"), Some(base), local.0, local.1);
+        checkout(repo);
+        commit(repo, None, Some(base), remote.0, remote.1)
+    }
+
+    /// Merges a commit into current branch.
+    fn merge(repo: &Repository, oid: Oid, strategy: MergeStrategy) -> Result<(), Error> {
+        let commit = repo.find_annotated_commit(oid).unwrap();
+        merge_commit(repo, &branch(repo), commit, strategy)
+    }
+
+    #[test]
+    fn merge_fast_forward() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let remote = commit(&repo, None, Some(head(&repo)), "README", "remote\n");
+        merge(&repo, remote, MergeStrategy::Abort).unwrap();
+        assert_eq!(head(&repo), remote);
+        assert_eq!(read(&repo, "README"), "remote\n");
+    }
+
+    #[test]
+    fn merge_without_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let remote = diverged(&repo, ("local", "local\n"), ("remote", "remote\n"));
+        let local = head(&repo);
+        merge(&repo, remote, MergeStrategy::Abort).unwrap();
+        let merged = repo.find_commit(head(&repo)).unwrap();
+        assert_eq!(merged.parent_ids().collect::<Vec<_>>(), [local, remote]);
+        assert_eq!(read(&repo, "local"), "local\n");
+        assert_eq!(read(&repo, "remote"), "remote\n");
+    }
+
+    #[test]
+    fn merge_conflict_abort() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let remote = diverged(&repo, ("README", "local\n"), ("README", "remote\n"));
+        let local = head(&repo);
+        let err = merge(&repo, remote, MergeStrategy::Abort).unwrap_err();
+        assert!(err.message().contains("conflicts in: README"), "{}", err);
+        assert_eq!(head(&repo), local);
+        assert_eq!(read(&repo, "README"), "local\n");
+    }
+
+    #[test]
+    fn merge_conflict_reset() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let remote = diverged(&repo, ("README", "local\n"), ("README", "remote\n"));
+        merge(&repo, remote, MergeStrategy::Reset).unwrap();
+        assert_eq!(head(&repo), remote);
+        assert_eq!(read(&repo, "README"), "remote\n");
+    }
+
+    #[test]
+    fn merge_conflict_ours() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let remote = diverged(&repo, ("README", "local\n"), ("README", "remote\n"));
+        let local = head(&repo);
+        merge(&repo, remote, MergeStrategy::Ours).unwrap();
+        let merged = repo.find_commit(head(&repo)).unwrap();
+        assert_eq!(merged.parent_ids().collect::<Vec<_>>(), [local, remote]);
+        assert_eq!(read(&repo, "README"), "local\n");
+    }
+
+    #[test]
+    fn merge_unrelated_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let local = head(&repo);
+        let remote = commit(&repo, None, None, "README", "unrelated\n");
+        assert!(merge(&repo, remote, MergeStrategy::Abort).is_err());
+        assert!(merge(&repo, remote, MergeStrategy::Ours).is_err());
+        assert_eq!(head(&repo), local);
+        assert_eq!(read(&repo, "README"), "base\n");
+
+        merge(&repo, remote, MergeStrategy::Reset).unwrap();
+        assert_eq!(head(&repo), remote);
+        assert_eq!(read(&repo, "README"), "unrelated\n");
+    }
+
+    #[test]
+    fn pull_from_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = init_repo(&dir.path().join("upstream"));
+        let local = Repository::clone(
+            dir.path().join("upstream").to_str().unwrap(),
+            dir.path().join("local"),
+        )
+        .unwrap();
+        let pulled = commit(
+            &upstream,
+            Some("HEAD"),
+            Some(head(&upstream)),
+            "README",
+            "pulled\n",
+        );
+
+        let config = CredentialsConfig::default();
+        let mut auth = RemoteAuth::new(&config, None);
+        let mut remote = local.find_remote("origin").unwrap();
+        pull_remote(
+            &local,
+            &branch(&upstream),
+            &mut remote,
+            MergeStrategy::Abort,
+            &mut auth,
+        )
+        .unwrap();
+        assert_eq!(head(&local), pulled);
+        assert_eq!(read(&local, "README"), "pulled\n");
+    }
+}
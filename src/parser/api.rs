@@ -0,0 +1,343 @@
+//! Package public API surface extraction.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use failure::Error;
+use quote::ToTokens;
+use syn::{
+    Fields, ImplItem, Item, ItemEnum, ItemImpl, ItemStruct, ItemTrait, TraitItem, Visibility,
+};
+
+use super::modules::{parse_module_tree, Sources};
+use crate::{git::Repository, ws::Package};
+
+/// Kind of a public API change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ApiChange {
+    /// Public items were removed or changed.
+    Breaking,
+    /// Public items were only added.
+    Additive,
+    /// Public API didn't change.
+    None,
+}
+
+/// Public API surface of a package.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ApiSurface {
+    pub items: BTreeSet<String>,
+}
+
+/// Difference between two public API surfaces.
+#[derive(Debug, Default)]
+pub struct ApiDiff {
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl ApiDiff {
+    /// Creates a difference between old and new API surfaces.
+    pub fn new(old: &ApiSurface, new: &ApiSurface) -> Self {
+        ApiDiff {
+            removed: old.items.difference(&new.items).cloned().collect(),
+            added: new.items.difference(&old.items).cloned().collect(),
+        }
+    }
+
+    /// Classifies the API change.
+    pub fn kind(&self) -> ApiChange {
+        if !self.removed.is_empty() {
+            ApiChange::Breaking
+        } else if !self.added.is_empty() {
+            ApiChange::Additive
+        } else {
+            ApiChange::None
+        }
+    }
+}
+
+/// Extracts public API difference of a package library
+/// between HEAD and the working tree.
+pub fn api_diff(
+    repo: &Repository,
+    pkg: &Package,
+    disabled_cfgs: &[String],
+) -> Result<ApiDiff, Error> {
+    let lib_root = match lib_root(pkg) {
+        Some(root) => root,
+        None => return Ok(ApiDiff::default()),
+    };
+    let tree = repo.head_tree()?;
+    let head_sources = parse_module_tree(&lib_root, disabled_cfgs, |path| {
+        let content = repo.get_contents(&tree, path)?;
+        Ok(String::from_utf8_lossy(&content).into_owned())
+    });
+    let workdir_sources = parse_module_tree(&lib_root, disabled_cfgs, |path| {
+        Ok(std::fs::read_to_string(path)?)
+    });
+    // Items of modules failed to parse would be classified as removed
+    let sources = [("HEAD", &head_sources), ("working tree", &workdir_sources)];
+    for (revision, sources) in sources.iter() {
+        if !sources.diagnostics.is_empty() {
+            let diagnostics: Vec<String> =
+                sources.diagnostics.iter().map(|d| d.to_string()).collect();
+            return Err(format_err!(
+                "{} sources of {} failed to parse: {}",
+                revision,
+                pkg.name(),
+                diagnostics.join("; ")
+            ));
+        }
+    }
+    let head = ApiSurface::from_sources(&head_sources);
+    let workdir = ApiSurface::from_sources(&workdir_sources);
+    let diff = ApiDiff::new(&head, &workdir);
+    trace!(
+        "Package {} API change: {:?} (removed: {:?}, added: {:?})",
+        pkg.name(),
+        diff.kind(),
+        diff.removed,
+        diff.added
+    );
+    Ok(diff)
+}
+
+impl ApiSurface {
+    /// Creates API surface from public modules sources.
+    pub fn from_sources(sources: &Sources) -> Self {
+        let mut surface = ApiSurface::default();
+        for file in sources.files.iter().filter(|file| file.public) {
+            surface.add_items(&file.module, &file.syntax.items);
+        }
+        surface
+    }
+
+    fn add_items(&mut self, module: &str, items: &[Item]) {
+        for item in items {
+            self.add_item(module, item);
+        }
+    }
+
+    fn add(&mut self, kind: &str, path: String, signature: String) {
+        self.items
+            .insert(format!("{} {} {}", kind, path, signature));
+    }
+
+    fn add_item(&mut self, module: &str, item: &Item) {
+        let path = |ident: &syn::Ident| format!("{}::{}", module, ident);
+        match item {
+            Item::Fn(item) if is_pub(&item.vis) => {
+                self.add("fn", path(&item.sig.ident), tokens(&item.sig))
+            }
+            Item::Struct(item) if is_pub(&item.vis) => self.add_struct(&path(&item.ident), item),
+            Item::Enum(item) if is_pub(&item.vis) => self.add_enum(&path(&item.ident), item),
+            Item::Trait(item) if is_pub(&item.vis) => self.add_trait(&path(&item.ident), item),
+            Item::Type(item) if is_pub(&item.vis) => {
+                self.add("type", path(&item.ident), tokens(&item.ty))
+            }
+            Item::Const(item) if is_pub(&item.vis) => {
+                self.add("const", path(&item.ident), tokens(&item.ty))
+            }
+            Item::Static(item) if is_pub(&item.vis) => {
+                self.add("static", path(&item.ident), tokens(&item.ty))
+            }
+            Item::Use(item) if is_pub(&item.vis) => {
+                self.add("use", module.to_owned(), tokens(&item.tree))
+            }
+            Item::Mod(item) if is_pub(&item.vis) => {
+                if let Some((_, items)) = &item.content {
+                    self.add_items(&path(&item.ident), items);
+                }
+            }
+            Item::Impl(item) => self.add_impl(item),
+            Item::Macro(item) if is_exported(&item.attrs) => {
+                if let Some(ident) = &item.ident {
+                    self.add("macro", format!("crate::{}", ident), String::new());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn add_struct(&mut self, path: &str, item: &ItemStruct) {
+        self.add("struct", path.to_owned(), tokens(&item.generics));
+        self.add_fields(path, &item.fields, true);
+    }
+
+    fn add_enum(&mut self, path: &str, item: &ItemEnum) {
+        self.add("enum", path.to_owned(), tokens(&item.generics));
+        for variant in &item.variants {
+            let variant_path = format!("{}::{}", path, variant.ident);
+            self.add("variant", variant_path.clone(), String::new());
+            self.add_fields(&variant_path, &variant.fields, false);
+        }
+    }
+
+    fn add_fields(&mut self, path: &str, fields: &Fields, check_vis: bool) {
+        for (index, field) in fields.iter().enumerate() {
+            if check_vis && !is_pub(&field.vis) {
+                continue;
+            }
+            let name = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => index.to_string(),
+            };
+            self.add("field", format!("{}.{}", path, name), tokens(&field.ty));
+        }
+    }
+
+    fn add_trait(&mut self, path: &str, item: &ItemTrait) {
+        self.add(
+            "trait",
+            path.to_owned(),
+            format!("{} {}", tokens(&item.generics), tokens(&item.supertraits)),
+        );
+        for trait_item in &item.items {
+            match trait_item {
+                TraitItem::Method(method) => self.add(
+                    "trait-fn",
+                    format!("{}::{}", path, method.sig.ident),
+                    tokens(&method.sig),
+                ),
+                TraitItem::Const(item) => self.add(
+                    "trait-const",
+                    format!("{}::{}", path, item.ident),
+                    tokens(&item.ty),
+                ),
+                TraitItem::Type(item) => self.add(
+                    "trait-type",
+                    format!("{}::{}", path, item.ident),
+                    tokens(&item.bounds),
+                ),
+                _ => (),
+            }
+        }
+    }
+
+    fn add_impl(&mut self, item: &ItemImpl) {
+        let self_ty = tokens(&item.self_ty);
+        if let Some((_, trait_path, _)) = &item.trait_ {
+            // Trait implementations don't depend on module they are in
+            self.add("impl", tokens(trait_path), format!("for {}", self_ty));
+            return;
+        }
+        for impl_item in &item.items {
+            match impl_item {
+                ImplItem::Method(method) if is_pub(&method.vis) => self.add(
+                    "method",
+                    format!("{}::{}", self_ty, method.sig.ident),
+                    tokens(&method.sig),
+                ),
+                ImplItem::Const(item) if is_pub(&item.vis) => self.add(
+                    "assoc-const",
+                    format!("{}::{}", self_ty, item.ident),
+                    tokens(&item.ty),
+                ),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Returns library target root source file.
+fn lib_root(pkg: &Package) -> Option<PathBuf> {
+    let lib = pkg.targets().iter().find(|target| target.is_lib())?;
+    lib.src_path().path().map(|path| path.to_path_buf())
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    match vis {
+        Visibility::Public(_) => true,
+        _ => false,
+    }
+}
+
+fn is_exported(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("macro_export"))
+}
+
+fn tokens<T: ToTokens>(node: &T) -> String {
+    node.to_token_stream().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIB: &str = "
+        pub struct Config { pub name: String, cache: bool }
+        pub enum Mode { Fast, Slow(u32) }
+        pub fn run(config: &Config) -> bool { true }
+        fn helper() {}
+        impl Config { pub fn new() -> Self { unimplemented!() } fn private(&self) {} }
+        pub mod util { pub const LIMIT: usize = 10; }
+    ";
+
+    fn surface(src: &str) -> ApiSurface {
+        let file = syn::parse_file(src).unwrap();
+        let mut surface = ApiSurface::default();
+        surface.add_items("crate", &file.items);
+        surface
+    }
+
+    fn diff_with(new: &str) -> ApiDiff {
+        ApiDiff::new(&surface(LIB), &surface(new))
+    }
+
+    #[test]
+    fn unchanged_api() {
+        let diff = diff_with(
+            &LIB.replace("{ true }", "{ config.name.is_empty() }")
+                .replace("fn helper() {}", "fn helper() -> u32 { 1 }")
+                .replace("fn private(&self) {}", ""),
+        );
+        assert_eq!(diff.kind(), ApiChange::None);
+        assert!(diff.removed.is_empty() && diff.added.is_empty());
+    }
+
+    #[test]
+    fn added_items() {
+        let diff = diff_with(&format!(
+            "{}\npub fn stop() {{}}\nimpl Default for Mode {{ fn default() -> Self {{ Mode::Fast }} }}",
+            LIB
+        ));
+        assert_eq!(diff.kind(), ApiChange::Additive);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.added.len(), 2);
+        assert!(diff.added[0].starts_with("fn crate::stop "));
+        assert_eq!(diff.added[1], "impl Default for Mode");
+    }
+
+    #[test]
+    fn removed_items() {
+        let diff = diff_with(&LIB.replace("pub mod util", "mod util"));
+        assert_eq!(diff.kind(), ApiChange::Breaking);
+        assert_eq!(diff.removed, ["const crate::util::LIMIT usize"]);
+        assert!(diff.added.is_empty());
+
+        let diff = diff_with(&LIB.replace(", Slow(u32)", ""));
+        assert_eq!(diff.kind(), ApiChange::Breaking);
+        assert_eq!(
+            diff.removed,
+            [
+                "field crate::Mode::Slow.0 u32",
+                "variant crate::Mode::Slow "
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_items() {
+        let diff = diff_with(&LIB.replace("-> bool", "-> Option<bool>"));
+        assert_eq!(diff.kind(), ApiChange::Breaking);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.removed[0].starts_with("fn crate::run "));
+        assert!(diff.added[0].starts_with("fn crate::run "));
+
+        let diff = diff_with(&LIB.replace("pub name: String", "pub name: &'static str"));
+        assert_eq!(diff.kind(), ApiChange::Breaking);
+        assert_eq!(diff.removed, ["field crate::Config.name String"]);
+    }
+}
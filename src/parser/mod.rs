@@ -1,17 +1,17 @@
+mod api;
+mod modules;
 mod visitor;
 
-use std::fs::File;
-use std::io::Read;
+pub use self::api::*;
+pub use self::modules::*;
 
 use failure::Error;
+use hashbrown::HashMap;
 use syn::visit::Visit;
 
 use self::visitor::PackageVisitor;
 
-use crate::{
-    util::glob_source,
-    ws::{Package, Workspace},
-};
+use crate::ws::{Package, Workspace};
 
 /// Members collection.
 #[derive(Debug)]
@@ -20,30 +20,146 @@ pub struct Members {
     pub macros: Vec<String>,
     /// Packages members.
     pub packages: Vec<String>,
+    /// Packages and macro packages used only in test code.
+    pub test_packages: Vec<String>,
+    /// Sources which couldn't be read or parsed.
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 /// Extracts used workspace dependencies in package root.
-pub fn collect_members<'a>(workspace: &'a Workspace<'a>, pkg: &Package) -> Result<Members, Error> {
-    let files = glob_source(pkg);
-    let mut visitor = PackageVisitor::new(workspace);
-    for filename in files {
-        let mut file = File::open(&filename).expect("Unable to open file");
-
-        let mut src = String::new();
-        file.read_to_string(&mut src).expect("Unable to read file");
-
-        let syntax = syn::parse_file(&src).expect("Unable to parse file");
-        // println!("syntax={:?}", syntax);
-        visitor.visit_file(&syntax);
+pub fn collect_members<'a>(
+    workspace: &'a Workspace<'a>,
+    pkg: &Package,
+    disabled_cfgs: &[String],
+) -> Result<Members, Error> {
+    let sources = parse_package(pkg, disabled_cfgs);
+    // Source code refers to renamed dependencies by their keys
+    let aliases: HashMap<String, String> = pkg
+        .dependencies()
+        .iter()
+        .filter(|dep| dep.name_in_toml() != dep.package_name())
+        .map(|dep| {
+            let name = dep.name_in_toml().replace("-", "_");
+            (name, dep.package_name().to_string())
+        })
+        .collect();
+    let mut visitor = PackageVisitor::new(workspace, aliases);
+    for file in &sources.files {
+        visitor.in_test = file.test;
+        visitor.visit_file(&file.syntax);
     }
     visitor.dedup();
-    let packages = visitor
+    let packages: Vec<String> = visitor
         .packages
         .into_iter()
         .map(|pkg| pkg.name().to_string())
         .collect();
+    let test_packages = visitor
+        .test_packages
+        .into_iter()
+        .map(|pkg| pkg.name().to_string())
+        .filter(|name| !packages.contains(name) && !visitor.macros.contains(name))
+        .collect();
     Ok(Members {
         macros: visitor.macros,
         packages,
+        test_packages,
+        diagnostics: sources.diagnostics,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use git2::{IndexAddOption, Repository as GitRepository};
+
+    use crate::git::Repository;
+    use crate::ws::{cargo_config, cargo_workspace};
+
+    const APP_LIB: &str = "#[macro_use]\n\
+                           extern crate legacy;\n\
+                           \n\
+                           use bang::make;\n\
+                           use helpers::Builder as Make;\n\
+                           \n\
+                           #[derive(Debug, Make, derive_kit::Builder)]\n\
+                           pub struct Config;\n\
+                           \n\
+                           pub fn run() {\n    make!();\n    tools::run!();\n    println!(\"done\");\n}\n\
+                           \n\
+                           #[cfg(test)]\n\
+                           mod tests {\n    #[test]\n    fn run() {\n        checks::verify!();\n    }\n}\n";
+
+    /// Writes a file creating its parent directories.
+    fn write_file(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// Commits all files in a new repository.
+    fn commit_all(dir: &Path) {
+        let repo = GitRepository::init(dir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(&["*"], IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn macros_derives_and_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = [
+            "app",
+            "bang",
+            "checks",
+            "derive-kit",
+            "helpers",
+            "idle",
+            "legacy",
+            "tools",
+        ];
+        let quoted: Vec<String> = names.iter().map(|name| format!("{:?}", name)).collect();
+        write_file(
+            &dir.path().join("Cargo.toml"),
+            &format!("[workspace]\nmembers = [{}]\n", quoted.join(", ")),
+        );
+        for name in &names {
+            let manifest = format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+                name
+            );
+            let lib = if *name == "app" { APP_LIB } else { "" };
+            write_file(&dir.path().join(name).join("Cargo.toml"), &manifest);
+            write_file(&dir.path().join(name).join("src/lib.rs"), lib);
+        }
+        commit_all(dir.path());
+
+        let config = cargo_config(dir.path().to_path_buf());
+        let cargo = cargo_workspace(&config).unwrap();
+        let mut repo = Repository::open(dir.path()).unwrap();
+        let workspace = Workspace::new(&cargo, &mut repo).unwrap();
+        let app = workspace.find_package("app").unwrap();
+        let members = collect_members(&workspace, app, &[]).unwrap();
+
+        // Macros by path, `#[macro_use]` crates and imported macros and derives
+        assert_eq!(
+            members.macros,
+            vec!["bang", "derive-kit", "helpers", "legacy", "tools"]
+        );
+        // Derive paths in attributes are not package paths
+        assert_eq!(members.packages, vec!["bang", "helpers", "legacy", "tools"]);
+        assert_eq!(members.test_packages, vec!["checks"]);
+        assert!(members.diagnostics.is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+//! Module tree walking source parser.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use hashbrown::HashSet;
+use syn::{Attribute, Item, ItemMod, Lit, Meta, Visibility};
+
+use crate::ws::Package;
+
+/// Parsed source file.
+pub struct SourceFile {
+    /// Source file path.
+    pub path: PathBuf,
+    /// Module path, e.g. `crate::foo::bar`.
+    pub module: String,
+    /// True if module is reachable from crate root through public modules.
+    pub public: bool,
+    /// True if module is compiled only for tests, benches or examples.
+    pub test: bool,
+    /// Parsed file syntax.
+    pub syntax: syn::File,
+}
+
+/// Source file parsing diagnostic.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// Sources parsed by walking module trees.
+#[derive(Default)]
+pub struct Sources {
+    pub files: Vec<SourceFile>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parses sources of all package targets from the file system.
+pub fn parse_package(pkg: &Package, disabled_cfgs: &[String]) -> Sources {
+    let mut roots: Vec<(PathBuf, bool)> = pkg
+        .targets()
+        .iter()
+        .filter_map(|target| {
+            let test = target.is_test() || target.is_bench() || target.is_example();
+            target
+                .src_path()
+                .path()
+                .map(|path| (path.to_path_buf(), test))
+        })
+        .collect();
+    // Library and binary roots are walked before test roots
+    roots.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+    roots.dedup_by(|a, b| a.0 == b.0);
+    let mut walker = ModuleWalker::new(disabled_cfgs, |path: &Path| {
+        Ok(std::fs::read_to_string(path)?)
+    });
+    for (root, test) in roots {
+        walker.walk_root(&root, test);
+    }
+    walker.sources
+}
+
+/// Walks module tree from a crate root reading files with a function.
+pub fn parse_module_tree<F>(root: &Path, disabled_cfgs: &[String], read: F) -> Sources
+where
+    F: Fn(&Path) -> Result<String, Error>,
+{
+    let mut walker = ModuleWalker::new(disabled_cfgs, read);
+    walker.walk_root(root, false);
+    walker.sources
+}
+
+/// Module location in a source tree.
+struct Module {
+    /// Module path.
+    path: String,
+    /// True if module and its parents are public.
+    public: bool,
+    /// True if module or any of its parents is test only.
+    test: bool,
+    /// Directory of child modules files.
+    dir: PathBuf,
+}
+
+struct ModuleWalker<'a, F> {
+    disabled_cfgs: &'a [String],
+    read: F,
+    visited: HashSet<PathBuf>,
+    sources: Sources,
+}
+
+impl<'a, F> ModuleWalker<'a, F>
+where
+    F: Fn(&Path) -> Result<String, Error>,
+{
+    fn new(disabled_cfgs: &'a [String], read: F) -> Self {
+        ModuleWalker {
+            disabled_cfgs,
+            read,
+            visited: HashSet::default(),
+            sources: Sources::default(),
+        }
+    }
+
+    fn walk_root(&mut self, root: &Path, test: bool) {
+        let module = Module {
+            path: "crate".to_owned(),
+            public: true,
+            test,
+            dir: root.parent().unwrap_or(Path::new("")).to_path_buf(),
+        };
+        self.walk_file(root, module);
+    }
+
+    fn walk_file(&mut self, path: &Path, module: Module) {
+        if !self.visited.insert(path.to_path_buf()) {
+            return;
+        }
+        trace!("Parsing module {} file {:?}", module.path, path);
+        let src = match (self.read)(path) {
+            Ok(src) => src,
+            Err(err) => return self.diagnostic(path, format!("unable to read file: {}", err)),
+        };
+        let syntax = match syn::parse_file(&src) {
+            Ok(syntax) => syntax,
+            Err(err) => return self.diagnostic(path, format!("unable to parse file: {}", err)),
+        };
+        let file_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        self.walk_items(&syntax.items, &module, &file_dir);
+        self.sources.files.push(SourceFile {
+            path: path.to_path_buf(),
+            module: module.path,
+            public: module.public,
+            test: module.test,
+            syntax,
+        });
+    }
+
+    fn walk_items(&mut self, items: &[Item], parent: &Module, file_dir: &Path) {
+        for item in items {
+            if let Item::Mod(item) = item {
+                self.walk_mod(item, parent, file_dir);
+            }
+        }
+    }
+
+    fn walk_mod(&mut self, item: &ItemMod, parent: &Module, file_dir: &Path) {
+        if self.is_cfg_disabled(&item.attrs) {
+            trace!(
+                "Skipping cfg disabled module {}::{}",
+                parent.path,
+                item.ident
+            );
+            return;
+        }
+        let name = item.ident.to_string();
+        let path_attr = path_attribute(&item.attrs);
+        let module_path = format!("{}::{}", parent.path, name);
+        let public = parent.public
+            && match item.vis {
+                Visibility::Public(_) => true,
+                _ => false,
+            };
+        let test = parent.test || is_cfg_test(&item.attrs);
+        if let Some((_, items)) = &item.content {
+            // Inline module, nested file modules live in its directory
+            let dir = match &path_attr {
+                Some(path) => parent.dir.join(path),
+                None => parent.dir.join(&name),
+            };
+            let module = Module {
+                path: module_path,
+                public,
+                test,
+                dir,
+            };
+            self.walk_items(items, &module, file_dir);
+            return;
+        }
+        let (file, dir) = match path_attr {
+            // Path is relative to directory of current file
+            // and nested modules are relative to the module file
+            Some(path) => {
+                let file = file_dir.join(path);
+                let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+                (file, dir)
+            }
+            None => {
+                let file = parent.dir.join(format!("{}.rs", name));
+                if self.exists(&file) {
+                    (file, parent.dir.join(&name))
+                } else {
+                    (
+                        parent.dir.join(&name).join("mod.rs"),
+                        parent.dir.join(&name),
+                    )
+                }
+            }
+        };
+        let module = Module {
+            path: module_path,
+            public,
+            test,
+            dir,
+        };
+        self.walk_file(&file, module);
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        (self.read)(path).is_ok()
+    }
+
+    /// Returns true if any `cfg` attribute matches configured disabled predicates.
+    fn is_cfg_disabled(&self, attrs: &[Attribute]) -> bool {
+        attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("cfg"))
+            .any(|attr| {
+                let predicate = normalize_cfg(&attr.tokens.to_string());
+                self.disabled_cfgs
+                    .iter()
+                    .any(|cfg| normalize_cfg(cfg) == predicate)
+            })
+    }
+
+    fn diagnostic(&mut self, path: &Path, message: String) {
+        trace!("Source diagnostic {:?}: {}", path, message);
+        self.sources.diagnostics.push(Diagnostic {
+            path: path.to_path_buf(),
+            message,
+        });
+    }
+}
+
+/// Returns value of a `#[path = "..."]` attribute.
+fn path_attribute(attrs: &[Attribute]) -> Option<PathBuf> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("path"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(lit) => Some(PathBuf::from(lit.value())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .next()
+}
+
+/// Returns true if item has a `#[cfg(test)]` attribute.
+pub(crate) fn is_cfg_test(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .any(|attr| normalize_cfg(&attr.tokens.to_string()) == "test")
+}
+
+/// Normalizes `cfg` predicate for comparison.
+fn normalize_cfg(cfg: &str) -> String {
+    let cfg: String = cfg.chars().filter(|c| !c.is_whitespace()).collect();
+    match cfg.strip_prefix("(").and_then(|cfg| cfg.strip_suffix(")")) {
+        Some(inner) => inner.to_owned(),
+        None => cfg,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes files relative to a directory.
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        for (path, contents) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn walk_module_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        write_files(
+            dir.path(),
+            &[
+                (
+                    "src/lib.rs",
+                    "pub mod api;\n\
+                     mod internal;\n\
+                     #[path = \"other/renamed.rs\"]\n\
+                     pub mod custom;\n\
+                     #[cfg(feature = \"legacy\")]\n\
+                     mod legacy;\n\
+                     #[cfg(test)]\n\
+                     mod tests;\n\
+                     pub mod inline {\n    pub mod nested;\n}\n\
+                     pub mod missing;\n\
+                     pub mod broken;\n",
+                ),
+                ("src/api.rs", "pub mod v1;\n"),
+                ("src/api/v1.rs", "pub fn get() {}\n"),
+                ("src/internal/mod.rs", "pub mod deep;\n"),
+                ("src/internal/deep.rs", ""),
+                ("src/other/renamed.rs", "pub mod child;\n"),
+                ("src/other/child.rs", ""),
+                ("src/tests.rs", ""),
+                ("src/inline/nested.rs", ""),
+                ("src/broken.rs", "fn broken( {}\n"),
+            ],
+        );
+        let disabled = vec!["feature = \"legacy\"".to_owned()];
+        let sources = parse_module_tree(&dir.path().join("src/lib.rs"), &disabled, |path| {
+            Ok(std::fs::read_to_string(path)?)
+        });
+
+        let mut files: Vec<_> = sources
+            .files
+            .iter()
+            .map(|file| {
+                let path = file.path.strip_prefix(dir.path()).unwrap();
+                (
+                    file.module.as_str(),
+                    path.to_str().unwrap().to_owned(),
+                    file.public,
+                    file.test,
+                )
+            })
+            .collect();
+        files.sort();
+        let expected = vec![
+            ("crate", "src/lib.rs", true, false),
+            ("crate::api", "src/api.rs", true, false),
+            ("crate::api::v1", "src/api/v1.rs", true, false),
+            ("crate::custom", "src/other/renamed.rs", true, false),
+            ("crate::custom::child", "src/other/child.rs", true, false),
+            ("crate::inline::nested", "src/inline/nested.rs", true, false),
+            ("crate::internal", "src/internal/mod.rs", false, false),
+            (
+                "crate::internal::deep",
+                "src/internal/deep.rs",
+                false,
+                false,
+            ),
+            ("crate::tests", "src/tests.rs", false, true),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(module, path, public, test)| (module, path.to_owned(), public, test))
+            .collect();
+        assert_eq!(files, expected);
+
+        // Disabled `legacy` module is skipped without a diagnostic
+        let mut diagnostics: Vec<_> = sources
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let path = diagnostic.path.strip_prefix(dir.path()).unwrap();
+                (
+                    path.to_str().unwrap().to_owned(),
+                    diagnostic.message.clone(),
+                )
+            })
+            .collect();
+        diagnostics.sort();
+        assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].0, "src/broken.rs");
+        assert!(diagnostics[0].1.starts_with("unable to parse file"));
+        assert_eq!(diagnostics[1].0, "src/missing/mod.rs");
+        assert!(diagnostics[1].1.starts_with("unable to read file"));
+    }
+
+    #[test]
+    fn cfg_predicates() {
+        let file: syn::File =
+            syn::parse_str("#[cfg(test)] mod a;\n#[cfg( feature = \"x\" )] mod b;").unwrap();
+        let attrs: Vec<&[Attribute]> = file
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Mod(item) => item.attrs.as_slice(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert!(is_cfg_test(attrs[0]));
+        assert!(!is_cfg_test(attrs[1]));
+
+        let disabled = vec!["feature=\"x\"".to_owned()];
+        let walker = ModuleWalker::new(&disabled, |_: &Path| Ok(String::new()));
+        assert!(!walker.is_cfg_disabled(attrs[0]));
+        assert!(walker.is_cfg_disabled(attrs[1]));
+    }
+}
@@ -1,23 +1,41 @@
 //! Package source code visitor.
 
+use hashbrown::HashMap;
 use syn::visit::{self, Visit};
-use syn::{Ident, ItemExternCrate, Macro, PathSegment, UsePath};
+use syn::{
+    Attribute, File, Ident, ItemExternCrate, ItemMod, ItemUse, Macro, Meta, NestedMeta, Path,
+    PathSegment, UsePath, UseTree,
+};
 
 use crate::ws::{Package, Workspace};
 
+use super::modules::is_cfg_test;
+
 /// Package source code visitor.
 pub struct PackageVisitor<'a> {
     workspace: &'a Workspace<'a>,
     pub macros: Vec<String>,
     pub packages: Vec<&'a Package<'a>>,
+    /// Packages and macro packages used in test code.
+    pub test_packages: Vec<&'a Package<'a>>,
+    /// True if visited code is compiled only for tests.
+    pub in_test: bool,
+    /// Crate names of renamed dependencies with package names.
+    aliases: HashMap<String, String>,
+    /// Names imported in current file with crates they come from.
+    imports: HashMap<String, String>,
 }
 
 impl<'a> PackageVisitor<'a> {
-    pub fn new(workspace: &'a Workspace<'a>) -> Self {
+    pub fn new(workspace: &'a Workspace<'a>, aliases: HashMap<String, String>) -> Self {
         PackageVisitor {
             workspace,
             macros: Vec::new(),
             packages: Vec::new(),
+            test_packages: Vec::new(),
+            in_test: false,
+            aliases,
+            imports: HashMap::default(),
         }
     }
 
@@ -28,29 +46,119 @@ impl<'a> PackageVisitor<'a> {
             .sort_by(|a, b| a.name().as_str().cmp(b.name().as_str()));
         self.packages
             .dedup_by(|a, b| a.name().as_str() == b.name().as_str());
+        self.test_packages
+            .sort_by(|a, b| a.name().as_str().cmp(b.name().as_str()));
+        self.test_packages
+            .dedup_by(|a, b| a.name().as_str() == b.name().as_str());
+    }
+
+    /// Finds workspace package by a crate name used in source code.
+    fn find_package(&self, name: &str) -> Option<&'a Package<'a>> {
+        match self.aliases.get(&name.replace("-", "_")) {
+            Some(package) => self.workspace.find_package(package),
+            None => self.workspace.find_package(&name.replace("_", "-")),
+        }
     }
 
     fn register_pkg(&mut self, ident: &Ident) {
-        let name = ident.to_string().replace("_", "-");
-        if let Some(pkg) = self.workspace.find_package(&name) {
-            self.packages.push(pkg);
+        if let Some(pkg) = self.find_package(&ident.to_string()) {
+            if self.in_test {
+                self.test_packages.push(pkg);
+            } else {
+                self.packages.push(pkg);
+            }
         }
     }
 
     fn register_macro(&mut self, ident: &Ident) {
-        // let name = ident.to_string().replace("_", "-");
-        // if let Some(pkg) = self.workspace.find_package(&name) {
-        //     self.packages.push(pkg);
-        // }
+        self.register_macro_crate(&ident.to_string());
+    }
+
+    fn register_macro_crate(&mut self, name: &str) {
+        if let Some(pkg) = self.find_package(name) {
+            trace!("Macro used from package {}", pkg.name());
+            if self.in_test {
+                self.test_packages.push(pkg);
+            } else {
+                self.macros.push(pkg.name().to_string());
+            }
+        }
+    }
+
+    /// Registers a macro, derive or attribute macro by its path.
+    /// Single segment paths are resolved through imports in current file.
+    fn register_macro_path(&mut self, path: &Path) {
+        let first = match path.segments.first() {
+            Some(segment) => &segment.ident,
+            None => return,
+        };
+        if path.segments.len() > 1 || path.leading_colon.is_some() {
+            self.register_macro(first);
+        } else if let Some(krate) = self.imports.get(&first.to_string()).cloned() {
+            self.register_macro_crate(&krate);
+        }
+    }
+
+    /// Collects names imported with `use` tree.
+    fn register_use_tree(&mut self, tree: &UseTree, root: Option<&str>) {
+        match tree {
+            UseTree::Path(path) => {
+                let ident = path.ident.to_string();
+                let root = root.unwrap_or(ident.as_str()).to_owned();
+                self.register_use_tree(&path.tree, Some(&root));
+            }
+            UseTree::Name(name) => {
+                if let Some(root) = root {
+                    self.imports.insert(name.ident.to_string(), root.to_owned());
+                }
+            }
+            UseTree::Rename(rename) => {
+                let root = root
+                    .map(|root| root.to_owned())
+                    .unwrap_or_else(|| rename.ident.to_string());
+                self.imports.insert(rename.rename.to_string(), root);
+            }
+            UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.register_use_tree(tree, root);
+                }
+            }
+            UseTree::Glob(_) => {}
+        }
     }
 }
 
 impl<'a, 'ast> Visit<'ast> for PackageVisitor<'a> {
+    fn visit_file(&mut self, node: &'ast File) {
+        // Imports are resolved per file
+        self.imports.clear();
+        visit::visit_file(self, node);
+    }
+
     fn visit_item_extern_crate(&mut self, node: &'ast ItemExternCrate) {
         self.register_pkg(&node.ident);
+        if node
+            .attrs
+            .iter()
+            .any(|attr| attr.path.is_ident("macro_use"))
+        {
+            self.register_macro(&node.ident);
+        }
         visit::visit_item_extern_crate(self, node);
     }
 
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        let in_test = self.in_test;
+        self.in_test = in_test || is_cfg_test(&node.attrs);
+        visit::visit_item_mod(self, node);
+        self.in_test = in_test;
+    }
+
+    fn visit_item_use(&mut self, node: &'ast ItemUse) {
+        self.register_use_tree(&node.tree, None);
+        visit::visit_item_use(self, node);
+    }
+
     fn visit_use_path(&mut self, node: &'ast UsePath) {
         self.register_pkg(&node.ident);
         visit::visit_use_path(self, node);
@@ -61,15 +169,23 @@ impl<'a, 'ast> Visit<'ast> for PackageVisitor<'a> {
         visit::visit_path_segment(self, node);
     }
 
+    fn visit_attribute(&mut self, node: &'ast Attribute) {
+        if node.path.is_ident("derive") {
+            if let Ok(Meta::List(list)) = node.parse_meta() {
+                for nested in list.nested.iter() {
+                    if let NestedMeta::Meta(Meta::Path(path)) = nested {
+                        self.register_macro_path(path);
+                    }
+                }
+            }
+        } else {
+            self.register_macro_path(&node.path);
+        }
+        visit::visit_attribute(self, node);
+    }
+
     fn visit_macro(&mut self, node: &'ast Macro) {
-        // if node.path.segments.first().unwrap().ident == "macro_rules" {
-        //     trace!(
-        //         "Macro: {:?}",
-        //         // node.path.segments.first().unwrap().ident.to_string()
-        //         node
-        //     );
-        // }
-        self.register_macro(&node.path.segments.first().unwrap().ident);
+        self.register_macro_path(&node.path);
         visit::visit_macro(self, node);
     }
 }
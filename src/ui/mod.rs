@@ -1,23 +1,26 @@
 pub mod commit;
 pub mod packages;
+mod prompter;
 pub mod update;
 
+pub use self::prompter::*;
+
 use console::Style;
-use dialoguer::{theme::ColorfulTheme, Confirmation, Select};
+use dialoguer::theme::ColorfulTheme;
 
 /// Selects one choice from the list.
-pub fn select_from_list<T: ToString>(prompt: &str, list: &[T]) -> std::io::Result<Option<usize>> {
-    Select::with_theme(&default_theme())
-        .with_prompt(prompt)
-        .items(list)
-        .interact_opt()
+pub fn select_from_list<T: ToString>(
+    prompter: &mut dyn Prompter,
+    prompt: &str,
+    list: &[T],
+) -> std::io::Result<Option<usize>> {
+    let items: Vec<String> = list.iter().map(|item| item.to_string()).collect();
+    prompter.select(prompt, &items)
 }
 
 /// Asks for a confirmation.
-pub fn confirm(text: &str) -> std::io::Result<bool> {
-    Confirmation::with_theme(&default_theme())
-        .with_text(text)
-        .interact()
+pub fn confirm(prompter: &mut dyn Prompter, text: &str) -> std::io::Result<bool> {
+    prompter.confirm(text)
 }
 
 pub fn default_theme() -> ColorfulTheme {
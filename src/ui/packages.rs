@@ -1,16 +1,14 @@
 use colored::Colorize;
-use dialoguer::Checkboxes;
 
 use crate::{
-    ui::select_from_list,
+    ui::{select_from_list, Prompter},
     util::{Bump, Update},
     ws::{Package, Workspace},
 };
 
-use super::default_theme;
-
 /// Selects changed package from workspace.
 pub fn select_changed<'a, 'i>(
+    prompter: &mut dyn Prompter,
     workspace: &'a Workspace<'i>,
 ) -> std::io::Result<Option<&'a Package<'i>>> {
     let packages: Vec<_> = workspace
@@ -32,27 +30,25 @@ pub fn select_changed<'a, 'i>(
             )
         })
         .collect();
-    Ok(select_from_list("Pick a package to commit", &names)?
-        .and_then(|selection| packages.get(selection))
-        .map(|s| *s))
+    Ok(
+        select_from_list(prompter, "Pick a package to commit", &names)?
+            .and_then(|selection| packages.get(selection))
+            .map(|s| *s),
+    )
 }
 
 /// Prompts to select dependencies to update.
 pub fn select_packages<'a>(
+    prompter: &mut dyn Prompter,
     prompt: &str,
     dependants: &Vec<&'a Package<'a>>,
     update: &Update,
     default: bool,
-) -> (Vec<&'a Package<'a>>, Vec<&'a Package<'a>>) {
+) -> std::io::Result<(Vec<&'a Package<'a>>, Vec<&'a Package<'a>>)> {
     if dependants.len() > 0 || update.as_bump().is_none() {
         let bump = update.as_bump().unwrap();
         let (checkboxes, defaults_update) = dependant_choices(&dependants, bump, default);
-        let selections = Checkboxes::with_theme(&default_theme())
-            .with_prompt(prompt)
-            .items(&checkboxes)
-            .defaults(&defaults_update)
-            .interact()
-            .unwrap();
+        let selections = prompter.checkboxes(prompt, &checkboxes, &defaults_update)?;
         let update_packages: Vec<_> = selections
             .clone()
             .into_iter()
@@ -69,9 +65,9 @@ pub fn select_packages<'a>(
                 }
             })
             .collect();
-        (update_packages, packages_to_git)
+        Ok((update_packages, packages_to_git))
     } else {
-        (vec![], vec![])
+        Ok((vec![], vec![]))
     }
 }
 
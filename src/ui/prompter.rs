@@ -0,0 +1,434 @@
+//! User interaction abstraction.
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use console::Term;
+use dialoguer::{Checkboxes, Confirmation, Input, PasswordInput, Select};
+use serde_json::Value;
+
+use super::default_theme;
+
+/// Source of answers to user prompts.
+pub trait Prompter {
+    /// Selects one item from the list, `None` if cancelled.
+    fn select(&mut self, prompt: &str, items: &[String]) -> Result<Option<usize>>;
+
+    /// Selects any number of items from the list.
+    fn checkboxes(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>>;
+
+    /// Asks for a confirmation.
+    fn confirm(&mut self, text: &str) -> Result<bool>;
+
+    /// Asks for a commit header between minimal and maximal length.
+    fn header(&mut self, prompt: &str, min: usize, max: usize) -> Result<String>;
+
+    /// Asks for a multi-line message, `None` if empty.
+    fn message(&mut self, prompt: &str) -> Result<Option<Vec<String>>>;
+
+    /// Asks for a hidden password, `None` if empty or not available.
+    fn password(&mut self, prompt: &str) -> Result<Option<String>>;
+}
+
+/// Terminal prompter.
+pub struct TermPrompter;
+
+impl Prompter for TermPrompter {
+    fn select(&mut self, prompt: &str, items: &[String]) -> Result<Option<usize>> {
+        Select::with_theme(&default_theme())
+            .with_prompt(prompt)
+            .items(items)
+            .interact_opt()
+    }
+
+    fn checkboxes(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>> {
+        Checkboxes::with_theme(&default_theme())
+            .with_prompt(prompt)
+            .items(items)
+            .defaults(defaults)
+            .interact()
+    }
+
+    fn confirm(&mut self, text: &str) -> Result<bool> {
+        Confirmation::with_theme(&default_theme())
+            .with_text(text)
+            .interact()
+    }
+
+    fn header(&mut self, prompt: &str, min: usize, max: usize) -> Result<String> {
+        let name = prompt.to_owned();
+        Input::with_theme(&default_theme())
+            .with_prompt(prompt)
+            .allow_empty(false)
+            .validate_with(move |text: &str| validate_header(&name, text, min, max))
+            .interact()
+    }
+
+    fn message(&mut self, prompt: &str) -> Result<Option<Vec<String>>> {
+        // Prompts are kept out of stdout, which may hold a release report
+        let term = Term::stderr();
+        term.write_line(&format!("{}:", prompt))?;
+        let mut lines = Vec::new();
+        let mut last_empty = false;
+        loop {
+            let line = term.read_line()?;
+            if line.len() == 0 {
+                if last_empty {
+                    break;
+                }
+                last_empty = true;
+            }
+            lines.push(line.trim().to_owned());
+        }
+        term.clear_last_lines(lines.len() + 2)?;
+        if lines.len() <= 1 && last_empty {
+            Ok(None)
+        } else {
+            Ok(Some(lines))
+        }
+    }
+
+    fn password(&mut self, prompt: &str) -> Result<Option<String>> {
+        if !console::user_attended() {
+            return Ok(None);
+        }
+        let password = PasswordInput::with_theme(&default_theme())
+            .with_prompt(prompt)
+            .allow_empty_password(true)
+            .interact()?;
+        Ok(Some(password).filter(|password| !password.is_empty()))
+    }
+}
+
+/// Scripted answer to a prompt.
+///
+/// Items are answered with an index or the first word of an item,
+/// e.g. a package name or an update kind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Answer {
+    /// Expected prompt, checked if not empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Answer value.
+    pub answer: Value,
+}
+
+/// Prompter answering from a JSON file with a list of answers.
+pub struct ScriptedPrompter {
+    answers: std::vec::IntoIter<Answer>,
+}
+
+impl ScriptedPrompter {
+    /// Creates a prompter from a list of answers.
+    pub fn new(answers: Vec<Answer>) -> Self {
+        ScriptedPrompter {
+            answers: answers.into_iter(),
+        }
+    }
+
+    /// Reads answers from a JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let answers = serde_json::from_str(&contents)?;
+        Ok(Self::new(answers))
+    }
+
+    /// Returns next answer checking it was scripted for the prompt.
+    fn next(&mut self, prompt: &str) -> Result<Value> {
+        let answer = self
+            .answers
+            .next()
+            .ok_or_else(|| invalid(format!("no scripted answer for {:?}", prompt)))?;
+        match answer.prompt {
+            Some(expected) if expected != prompt => Err(invalid(format!(
+                "expected prompt {:?}, got {:?}",
+                expected, prompt
+            ))),
+            _ => {
+                trace!("Scripted answer to {:?}: {}", prompt, answer.answer);
+                Ok(answer.answer)
+            }
+        }
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn select(&mut self, prompt: &str, items: &[String]) -> Result<Option<usize>> {
+        match self.next(prompt)? {
+            Value::Null => Ok(None),
+            value => find_item(items, &value).map(Some),
+        }
+    }
+
+    fn checkboxes(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>> {
+        match self.next(prompt)? {
+            Value::Null => Ok(defaults
+                .iter()
+                .enumerate()
+                .filter(|(_, checked)| **checked)
+                .map(|(index, _)| index)
+                .collect()),
+            Value::Array(values) => values.iter().map(|v| find_item(items, v)).collect(),
+            value => Err(invalid(format!("expected a list, got {}", value))),
+        }
+    }
+
+    fn confirm(&mut self, text: &str) -> Result<bool> {
+        match self.next(text)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(invalid(format!("expected a boolean, got {}", value))),
+        }
+    }
+
+    fn header(&mut self, prompt: &str, min: usize, max: usize) -> Result<String> {
+        match self.next(prompt)? {
+            Value::String(text) => {
+                validate_header(prompt, &text, min, max).map_err(invalid)?;
+                Ok(text)
+            }
+            value => Err(invalid(format!("expected a string, got {}", value))),
+        }
+    }
+
+    fn message(&mut self, prompt: &str) -> Result<Option<Vec<String>>> {
+        match self.next(prompt)? {
+            Value::Null => Ok(None),
+            Value::String(text) => Ok(Some(text.lines().map(|l| l.to_owned()).collect())),
+            Value::Array(values) => values
+                .into_iter()
+                .map(|value| match value {
+                    Value::String(line) => Ok(line),
+                    value => Err(invalid(format!("expected a string, got {}", value))),
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(Some),
+            value => Err(invalid(format!("expected a message, got {}", value))),
+        }
+    }
+
+    fn password(&mut self, prompt: &str) -> Result<Option<String>> {
+        match self.next(prompt)? {
+            Value::Null => Ok(None),
+            Value::String(password) => Ok(Some(password)),
+            value => Err(invalid(format!("expected a string, got {}", value))),
+        }
+    }
+}
+
+/// Prompter recording answers of an inner prompter to a JSON file.
+///
+/// File is rewritten after every answer and can be used by [`ScriptedPrompter`].
+/// Passwords are recorded as empty answers.
+pub struct RecordingPrompter {
+    inner: Box<dyn Prompter>,
+    path: PathBuf,
+    answers: Vec<Answer>,
+}
+
+impl RecordingPrompter {
+    /// Creates a prompter recording to a file.
+    pub fn new<P: Into<PathBuf>>(inner: Box<dyn Prompter>, path: P) -> Self {
+        RecordingPrompter {
+            inner,
+            path: path.into(),
+            answers: Vec::new(),
+        }
+    }
+
+    /// Records an answer and saves answers file.
+    fn record(&mut self, prompt: &str, answer: Value) -> Result<()> {
+        self.answers.push(Answer {
+            prompt: Some(prompt.to_owned()),
+            answer,
+        });
+        let contents = serde_json::to_string_pretty(&self.answers)?;
+        std::fs::write(&self.path, contents)
+    }
+}
+
+impl Prompter for RecordingPrompter {
+    fn select(&mut self, prompt: &str, items: &[String]) -> Result<Option<usize>> {
+        let selection = self.inner.select(prompt, items)?;
+        let answer = match selection {
+            Some(index) => item_answer(items, index),
+            None => Value::Null,
+        };
+        self.record(prompt, answer)?;
+        Ok(selection)
+    }
+
+    fn checkboxes(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>> {
+        let selections = self.inner.checkboxes(prompt, items, defaults)?;
+        let answer = selections
+            .iter()
+            .map(|index| item_answer(items, *index))
+            .collect();
+        self.record(prompt, Value::Array(answer))?;
+        Ok(selections)
+    }
+
+    fn confirm(&mut self, text: &str) -> Result<bool> {
+        let confirmed = self.inner.confirm(text)?;
+        self.record(text, Value::Bool(confirmed))?;
+        Ok(confirmed)
+    }
+
+    fn header(&mut self, prompt: &str, min: usize, max: usize) -> Result<String> {
+        let header = self.inner.header(prompt, min, max)?;
+        self.record(prompt, Value::String(header.clone()))?;
+        Ok(header)
+    }
+
+    fn message(&mut self, prompt: &str) -> Result<Option<Vec<String>>> {
+        let message = self.inner.message(prompt)?;
+        let answer = match &message {
+            Some(lines) => lines.iter().cloned().map(Value::String).collect(),
+            None => Value::Null,
+        };
+        self.record(prompt, answer)?;
+        Ok(message)
+    }
+
+    fn password(&mut self, prompt: &str) -> Result<Option<String>> {
+        let password = self.inner.password(prompt)?;
+        self.record(prompt, Value::Null)?;
+        Ok(password)
+    }
+}
+
+/// Validates commit header length.
+fn validate_header(
+    prompt: &str,
+    text: &str,
+    min: usize,
+    max: usize,
+) -> std::result::Result<(), String> {
+    if text.trim().len() < min {
+        Err(format!("{} is too short.", prompt))
+    } else if text.trim().len() > max {
+        Err(format!("{} is too long.", prompt))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns first word of an item without styling.
+fn item_key(item: &str) -> String {
+    console::strip_ansi_codes(item)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// Returns answer selecting an item, index if its first word is not unique.
+fn item_answer(items: &[String], index: usize) -> Value {
+    let key = item_key(&items[index]);
+    if items.iter().filter(|item| item_key(item) == key).count() == 1 {
+        Value::String(key)
+    } else {
+        Value::from(index)
+    }
+}
+
+/// Finds an item by its index or first word.
+fn find_item(items: &[String], value: &Value) -> Result<usize> {
+    let index = match value {
+        Value::Number(index) => index.as_u64().map(|index| index as usize),
+        Value::String(key) => items.iter().position(|item| item_key(item) == *key),
+        _ => None,
+    };
+    match index {
+        Some(index) if index < items.len() => Ok(index),
+        _ => Err(invalid(format!("no item {} in {:?}", value, items))),
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    /// Answers all kinds of prompts.
+    fn session(prompter: &mut dyn Prompter) -> Result<Value> {
+        let packages = items(&["a v0.1.0", "b v0.1.0", "c v0.1.0"]);
+        let selected = prompter.select("Pick a package", &packages)?;
+        let checked = prompter.checkboxes("Select", &packages, &[false, false, false])?;
+        let confirmed = prompter.confirm("Continue?")?;
+        let header = prompter.header("Commit header", 1, 50)?;
+        let message = prompter.message("Commit message")?;
+        let password = prompter.password("Passphrase")?;
+        Ok(serde_json::json!([
+            selected, checked, confirmed, header, message, password
+        ]))
+    }
+
+    #[test]
+    fn recorded_answers_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("answers.json");
+        let answers: Vec<Answer> = serde_json::from_str(
+            r#"[
+                {"answer": "b"},
+                {"answer": [0, "c"]},
+                {"answer": true},
+                {"answer": "new value"},
+                {"answer": ["first", "second"]},
+                {"answer": "secret"}
+            ]"#,
+        )
+        .unwrap();
+        let inner = Box::new(ScriptedPrompter::new(answers));
+        let recorded = session(&mut RecordingPrompter::new(inner, &path)).unwrap();
+        assert_eq!(
+            recorded,
+            serde_json::json!([1, [0, 2], true, "new value", ["first", "second"], "secret"])
+        );
+
+        let replayed = session(&mut ScriptedPrompter::from_file(&path).unwrap()).unwrap();
+        assert_eq!(
+            replayed,
+            serde_json::json!([1, [0, 2], true, "new value", ["first", "second"], null])
+        );
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("\"Pick a package\""));
+        assert!(!contents.contains("secret"));
+    }
+
+    #[test]
+    fn unexpected_prompt() {
+        let mut prompter = ScriptedPrompter::new(vec![Answer {
+            prompt: Some("Commit header".to_owned()),
+            answer: Value::from("header"),
+        }]);
+        let err = prompter.confirm("Continue?").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
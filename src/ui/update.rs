@@ -4,19 +4,65 @@ use cargo::core::Package;
 use colored::*;
 
 use crate::{
-    ui,
+    parser::{ApiChange, ApiDiff},
+    ui::{self, Prompter},
     util::{Bump, Update, UPDATES},
 };
 
 /// Prompts for a package update kind.
-pub fn prompt(pkg: &Package) -> std::io::Result<Option<&Update>> {
+/// Warns if selected update is too small for a breaking API change.
+pub fn prompt(
+    prompter: &mut dyn Prompter,
+    pkg: &Package,
+    api: Option<&ApiDiff>,
+) -> std::io::Result<Option<&'static Update>> {
     let ver = pkg.version();
-    let selection = ui::select_from_list(
-        &format!("Select update kind for {}", pkg.name()),
-        &update_choices(ver),
-    )?;
-    println!();
-    Ok(selection.and_then(|selection| UPDATES.get(selection)))
+    loop {
+        let selection = ui::select_from_list(
+            prompter,
+            &format!("Select update kind for {}", pkg.name()),
+            &update_choices(ver),
+        )?;
+        eprintln!();
+        let update = match selection.and_then(|selection| UPDATES.get(selection)) {
+            Some(update) => update,
+            None => return Ok(None),
+        };
+        match api {
+            Some(api) if api.kind() == ApiChange::Breaking && !allows_breaking(update, ver) => {
+                warn_breaking(pkg, update, api);
+                if ui::confirm(prompter, "Do you want to continue anyway?")? {
+                    return Ok(Some(update));
+                }
+            }
+            _ => return Ok(Some(update)),
+        }
+    }
+}
+
+/// Returns true if update allows breaking changes according to semver.
+fn allows_breaking(update: &Update, ver: &semver::Version) -> bool {
+    match update.as_bump() {
+        Some(Bump::Major) => true,
+        Some(Bump::Minor) => ver.major == 0,
+        Some(Bump::Patch) | None => false,
+    }
+}
+
+fn warn_breaking(pkg: &Package, update: &Update, api: &ApiDiff) {
+    eprintln!(
+        "{} {} update of {} contains breaking API changes:",
+        "Warning:".red().bold(),
+        update.name(),
+        pkg.name()
+    );
+    for item in api.removed.iter().take(10) {
+        eprintln!("  - {}", item.red());
+    }
+    if api.removed.len() > 10 {
+        eprintln!("  ... and {} more", api.removed.len() - 10);
+    }
+    eprintln!();
 }
 
 fn update_choices(ver: &semver::Version) -> Vec<String> {
@@ -0,0 +1,248 @@
+//! Format-preserving TOML document helpers.
+
+use toml_edit::{decorated, Document, Item, Table, Value};
+
+/// Dependency tables in a package manifest.
+pub(crate) const DEP_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Calls a function with every dependency table in a manifest,
+/// including `[target.'cfg(...)'.dependencies]` tables.
+pub(crate) fn for_each_dep_table<F: FnMut(&mut Table)>(doc: &mut Document, mut f: F) {
+    let root = doc.as_table_mut();
+    for name in DEP_TABLES {
+        if let Some(table) = root.get_mut(name).and_then(Item::as_table_mut) {
+            f(table);
+        }
+    }
+    if let Some(targets) = root.get_mut("target").and_then(Item::as_table_mut) {
+        for key in table_keys(targets) {
+            if let Some(target) = targets.get_mut(&key).and_then(Item::as_table_mut) {
+                for name in DEP_TABLES {
+                    if let Some(table) = target.get_mut(name).and_then(Item::as_table_mut) {
+                        f(table);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns keys of a table.
+pub(crate) fn table_keys(table: &Table) -> Vec<String> {
+    table.iter().map(|(key, _)| key.to_owned()).collect()
+}
+
+/// Returns keys of dependencies on a package in a dependency table,
+/// including dependencies renamed with `package = "name"`.
+pub(crate) fn dep_keys(table: &Table, package: &str) -> Vec<String> {
+    table
+        .iter()
+        .filter(|(key, item)| match get_str(item, "package") {
+            Some(name) => name == package,
+            None => *key == package,
+        })
+        .map(|(key, _)| key.to_owned())
+        .collect()
+}
+
+/// Returns a table under a path, creating it if it doesn't exist.
+pub(crate) fn table_mut<'a>(doc: &'a mut Document, keys: &[&str]) -> &'a mut Table {
+    let mut table = doc.as_table_mut();
+    for (index, key) in keys.iter().enumerate() {
+        let item = table.entry(key);
+        if !item.is_table() {
+            let mut new = Table::new();
+            // Parent tables don't need their own headers
+            new.set_implicit(index + 1 < keys.len());
+            *item = Item::Table(new);
+        }
+        table = item.as_table_mut().unwrap();
+    }
+    table
+}
+
+/// Returns a table under a path if it exists.
+pub(crate) fn find_table_mut<'a>(doc: &'a mut Document, keys: &[&str]) -> Option<&'a mut Table> {
+    let mut table = doc.as_table_mut();
+    for key in keys {
+        table = table.get_mut(key)?.as_table_mut()?;
+    }
+    Some(table)
+}
+
+/// Renames a key in a table under a path keeping order of entries,
+/// formatting and comments of the key.
+///
+/// `toml_edit` doesn't expose key formatting, the key is renamed
+/// in rendered document and accepted only if the reparsed table
+/// has the same keys with the old one renamed.
+pub(crate) fn rename_key(doc: &mut Document, path: &[&str], old: &str, new: &str) {
+    let keys = match find_table_mut(doc, path) {
+        Some(table) => table_keys(table),
+        None => return,
+    };
+    if old == new || !keys.iter().any(|key| key == old) {
+        return;
+    }
+    let expected: Vec<&str> = keys
+        .iter()
+        .map(|key| if key == old { new } else { key.as_str() })
+        .collect();
+    let text = doc.to_string();
+    for (old_repr, new_repr) in key_reprs(old, new) {
+        for (index, _) in text.match_indices(&old_repr) {
+            let renamed = format!(
+                "{}{}{}",
+                &text[..index],
+                new_repr,
+                &text[index + old_repr.len()..]
+            );
+            let mut renamed: Document = match renamed.parse() {
+                Ok(renamed) => renamed,
+                Err(_) => continue,
+            };
+            let is_renamed = find_table_mut(&mut renamed, path)
+                .map(|table| table_keys(table) == expected)
+                .unwrap_or(false);
+            if is_renamed {
+                *doc = renamed;
+                return;
+            }
+        }
+    }
+    trace!(
+        "Key {:?} not found in rendered table, moving it to the end",
+        old
+    );
+    if let Some(table) = find_table_mut(doc, path) {
+        if let Some(item) = table.remove(old) {
+            table.insert(new, item);
+        }
+    }
+}
+
+/// Returns possible representations of an old key
+/// with representations of a new key in the same style.
+fn key_reprs(old: &str, new: &str) -> Vec<(String, String)> {
+    let is_bare = |key: &str| {
+        key.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    let mut reprs = Vec::new();
+    if !old.contains(|c| c == '"' || c == '\\') && !new.contains(|c| c == '"' || c == '\\') {
+        reprs.push((format!("\"{}\"", old), format!("\"{}\"", new)));
+    }
+    if !old.contains('\'') && !new.contains('\'') {
+        reprs.push((format!("'{}'", old), format!("'{}'", new)));
+    }
+    if is_bare(old) {
+        let new_repr = if is_bare(new) {
+            new.to_owned()
+        } else {
+            format!("\"{}\"", new)
+        };
+        reprs.push((old.to_owned(), new_repr));
+    }
+    reprs
+}
+
+/// Returns string value of a key in a table or an inline table.
+pub(crate) fn get_str<'a>(item: &'a Item, key: &str) -> Option<&'a str> {
+    match item {
+        Item::Table(table) => table.get(key)?.as_str(),
+        Item::Value(Value::InlineTable(table)) => table.get(key)?.as_str(),
+        _ => None,
+    }
+}
+
+/// Sets string value of a key in a table or an inline table
+/// keeping formatting of the previous value.
+pub(crate) fn set_str(item: &mut Item, key: &str, value: &str) {
+    match item {
+        Item::Table(table) => set_item_str(table.entry(key), value),
+        Item::Value(Value::InlineTable(table)) => match table.get_mut(key) {
+            Some(old) => set_value_str(old, value),
+            None => {
+                table.get_or_insert(key, value);
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Removes a key from a table or an inline table.
+pub(crate) fn remove_key(item: &mut Item, key: &str) -> bool {
+    match item {
+        Item::Table(table) => table.remove(key).is_some(),
+        Item::Value(Value::InlineTable(table)) => table.remove(key).is_some(),
+        _ => false,
+    }
+}
+
+/// Returns keys of a dependency table or an inline table.
+pub(crate) fn item_keys(item: &Item) -> Vec<String> {
+    match item {
+        Item::Table(table) => table_keys(table),
+        Item::Value(Value::InlineTable(table)) => {
+            table.iter().map(|(key, _)| key.to_owned()).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Sets string value of an item keeping formatting of the previous value.
+pub(crate) fn set_item_str(item: &mut Item, value: &str) {
+    match item.as_value_mut() {
+        Some(old) => set_value_str(old, value),
+        None => *item = toml_edit::value(value),
+    }
+}
+
+/// Sets string value keeping formatting of the previous value.
+pub(crate) fn set_value_str(old: &mut Value, value: &str) {
+    let decor = old.decor();
+    let (prefix, suffix) = (decor.prefix().to_owned(), decor.suffix().to_owned());
+    *old = decorated(value.into(), &prefix, &suffix);
+}
+
+/// Dependency keys of sources other than path and registry version.
+const SOURCE_KEYS: &[&str] = &["git", "branch", "tag", "rev", "registry"];
+
+/// Sets version and path of a table or an inline table dependency in place
+/// keeping its form, options and formatting, other sources are removed.
+/// Returns false for plain version dependencies.
+pub(crate) fn set_dep_source(item: &mut Item, version: &str, path: Option<&str>) -> bool {
+    match item {
+        Item::Table(_) | Item::Value(Value::InlineTable(_)) => {}
+        _ => return false,
+    }
+    for key in SOURCE_KEYS {
+        remove_key(item, key);
+    }
+    set_str(item, "version", version);
+    match path {
+        Some(path) => set_str(item, "path", path),
+        None => {
+            remove_key(item, "path");
+        }
+    }
+    true
+}
+
+/// Returns version requirement of a dependency.
+/// Works for `dependency = "x.x.x"`, `dependency = { version = "x.x.x" }`
+/// and `[dependencies.dependency]` tables.
+pub(crate) fn dep_version(item: &Item) -> Option<&str> {
+    match item {
+        Item::Value(Value::String(_)) => item.as_str(),
+        _ => get_str(item, "version"),
+    }
+}
+
+/// Sets version requirement of a dependency keeping its formatting.
+pub(crate) fn set_dep_version(item: &mut Item, version: &str) {
+    match item {
+        Item::Value(Value::String(_)) => set_item_str(item, version),
+        _ => set_str(item, "version", version),
+    }
+}
@@ -1,35 +1,71 @@
 //! Manifest extension traits.
 
-use std::fs::File;
-use std::io::Write;
 use std::path::Path;
 
 use semver::Version;
+use toml_edit::{Document, InlineTable, Item, Value};
 
+use crate::updater::{update_requirement, RequirementPolicy};
 use crate::util::{Bump, BumpExt, CleanPath};
 use crate::ws::{Dependency, Package};
 
+use super::document::{
+    dep_keys, dep_version, find_table_mut, for_each_dep_table, get_str, item_keys, remove_key,
+    rename_key, set_dep_source, set_dep_version, set_item_str, set_str, table_keys, table_mut,
+};
+
 /// Workspace manifest extension trait.
 pub trait WorkspaceManifestExt: ManifestExt {
     /// Inserts new git replace for a package.
     fn git_replace(&mut self, package: &Package, remote_url: &str, rev: &str) {
-        self.lines_mut().push(format!(
-            "\"{}:{}\" = {{ git = \"{}\", rev = \"{}\" }}",
-            package.name(),
-            package.version(),
-            remote_url,
-            rev
-        ));
+        let key = format!("{}:{}", package.name(), package.version());
+        let mut entry = InlineTable::default();
+        entry.get_or_insert("git", remote_url);
+        entry.get_or_insert("rev", rev);
+        table_mut(self.document_mut(), &["replace"]).insert(&key, Item::Value(entry.into()));
     }
 
-    /// Bumps package version in manifest.
+    /// Inserts new git patch for a package in a registry.
+    fn git_patch(&mut self, registry: &str, package: &Package, remote_url: &str, rev: &str) {
+        let mut entry = InlineTable::default();
+        entry.get_or_insert("git", remote_url);
+        entry.get_or_insert("rev", rev);
+        set_patch(self.document_mut(), registry, package, entry);
+    }
+
+    /// Bumps package version in `replace` and `patch` sections.
     fn bump_replace_ver(&mut self, package: &Package, bump: Bump) {
-        change_replace(self.lines_mut(), package, bump);
+        change_replace(self.document_mut(), package, bump);
+        change_patch(self.document_mut(), package, bump);
+    }
+
+    /// Sets package path in `patch` section of a registry.
+    fn set_patch(&mut self, registry: &str, package: &Package, path: &Path) {
+        let mut entry = InlineTable::default();
+        entry.get_or_insert("path", path.clean_path_str());
+        set_patch(self.document_mut(), registry, package, entry);
+    }
+
+    /// Moves `replace` section entries into `patch` section of a registry.
+    /// Returns names of migrated packages.
+    fn migrate_replace(&mut self, registry: &str) -> Result<Vec<String>, failure::Error> {
+        migrate_replace(self.document_mut(), registry)
+    }
+
+    /// Bumps workspace version in a manifest table.
+    fn bump_workspace_ver(&mut self, table: &str, version: &Version, bump: Bump) {
+        change_table_ver(self.document_mut(), table, version, &version.bump(bump));
     }
 
     /// Bumps package version in manifest.
     fn set_replace(&mut self, package: &Package, path: &Path) {
-        set_replace(self.lines_mut(), package, path);
+        set_replace(self.document_mut(), package, path);
+    }
+
+    /// Removes package `replace` and `patch` entries with a source key
+    /// like `path` or `git`. Returns true if any entry was removed.
+    fn remove_overrides(&mut self, package: &Package, source: &str) -> bool {
+        remove_overrides(self.document_mut(), package, source)
     }
 }
 
@@ -42,173 +78,503 @@ pub trait PackageManifestExt: ManifestExt {
     fn bump_ver(&mut self, bump: Bump) {
         let ver = self.version().clone();
         let new = ver.bump(bump);
-        change_first_ver(self.lines_mut(), &ver, &new);
+        change_table_ver(self.document_mut(), "package", &ver, &new);
     }
 
     /// Sets package version in manifest.
     fn update_ver(&mut self, new: &Version) {
         let ver = self.version().clone();
-        change_first_ver(self.lines_mut(), &ver, new);
+        change_table_ver(self.document_mut(), "package", &ver, new);
     }
 
-    /// Updates dependency version in manifest.
-    fn update_dep(&mut self, name: &str, old: &Version, new: &Version) {
-        change_dep_ver(self.lines_mut(), name, old, new);
+    /// Updates dependency version requirement in manifest.
+    fn update_dep(
+        &mut self,
+        name: &str,
+        old: &Version,
+        new: &Version,
+        policy: RequirementPolicy,
+    ) -> Result<(), failure::Error> {
+        change_dep_ver(self.document_mut(), name, old, new, policy)
     }
 
     /// Updates dependency version in manifest.
     fn set_dep_path(&mut self, name: &str, path: &Path, ver: &Version) {
-        set_dep_path(self.lines_mut(), name, path, ver);
+        set_dep_path(self.document_mut(), name, path, ver);
     }
 
     /// Updates dependency version in manifest.
     fn set_dep_force(&mut self, dep: &Dependency, pkg: &Package, path: Option<&Path>) {
-        set_dep_force(self.lines_mut(), dep, pkg, path);
+        set_dep_force(self.document_mut(), dep, pkg, path);
+    }
+
+    /// Removes dependency path keeping its requirement and options.
+    fn strip_dep_path(&mut self, name: &str, ver: &Version) {
+        strip_dep_path(self.document_mut(), name, ver);
+    }
+
+    /// Removes dependency with a key from all dependency tables in manifest.
+    fn remove_dep(&mut self, key: &str) {
+        for_each_dep_table(self.document_mut(), |table| {
+            table.remove(key);
+        });
+    }
+
+    /// Adds dependency with version and path to `dependencies`
+    /// or `dev-dependencies` table.
+    fn add_dep(&mut self, name: &str, ver: &Version, path: &Path, dev: bool) {
+        let mut entry = InlineTable::default();
+        entry.get_or_insert("version", ver.to_string());
+        entry.get_or_insert("path", path.clean_path_str());
+        entry.fmt();
+        let table = if dev {
+            "dev-dependencies"
+        } else {
+            "dependencies"
+        };
+        table_mut(self.document_mut(), &[table]).insert(name, Item::Value(entry.into()));
     }
 }
 
 /// Manifest extension trait.
 pub trait ManifestExt {
-    /// Returns lines of a manifest.
-    fn lines(&self) -> &Vec<String>;
+    /// Returns manifest TOML document.
+    fn document(&self) -> &Document;
 
-    /// Returns mutable lines of a manifest.
-    fn lines_mut(&mut self) -> &mut Vec<String>;
+    /// Returns mutable manifest TOML document.
+    fn document_mut(&mut self) -> &mut Document;
 
     /// Returns content of a manifest.
     fn content(&self) -> String {
-        self.lines().join("\n")
+        self.document().to_string()
     }
 
     /// Saves TOML manifest to a destination.
     fn save<P: AsRef<Path>>(&self, dest: P) -> std::io::Result<()> {
-        let mut output = File::create(&dest)?;
-        for line in self.lines() {
-            output.write(line.as_bytes())?;
-            output.write(&['\n' as u8])?;
-        }
-        Ok(())
+        std::fs::write(dest, self.content())
     }
 }
 
 /// Changes package version in `replace` section.
-fn change_replace(lines: &mut Vec<String>, package: &Package, bump: Bump) {
-    let old = format!(":{}\"", package.version());
-    let new = format!(":{}\"", package.version().bump(bump));
-    let find_name = format!("\"{}:{}\"", package.name(), package.version());
-    for line in lines {
-        if line.starts_with(&find_name) {
-            *line = line.replace(&old, &new);
+fn change_replace(doc: &mut Document, package: &Package, bump: Bump) {
+    let old = format!("{}:{}", package.name(), package.version());
+    let new = format!("{}:{}", package.name(), package.version().bump(bump));
+    rename_key(doc, &["replace"], &old, &new);
+}
+
+/// Changes package version in `patch` sections of all registries.
+/// Only entries with a `version` key are changed.
+fn change_patch(doc: &mut Document, package: &Package, bump: Bump) {
+    let old = package.version().to_string();
+    let new = package.version().bump(bump).to_string();
+    let patch = match find_table_mut(doc, &["patch"]) {
+        Some(patch) => patch,
+        None => return,
+    };
+    for registry in table_keys(patch) {
+        let entry = patch
+            .get_mut(&registry)
+            .and_then(Item::as_table_mut)
+            .and_then(|table| table.get_mut(package.name().as_str()));
+        if let Some(entry) = entry {
+            if get_str(entry, "version") == Some(old.as_str()) {
+                set_str(entry, "version", &new);
+            }
         }
     }
 }
 
-/// Sets package version and path in `replace` section.
-fn set_replace(lines: &mut Vec<String>, package: &Package, path: &Path) {
-    let new_line = format!(
-        "\"{}:{}\" = {{ path = {:?} }}",
-        package.name(),
-        package.version(),
-        path.clean_path()
-    );
-    let find_name = format!("\"{}:", package.name());
-    for line in lines.iter_mut() {
-        if line.starts_with(&find_name) {
-            *line = new_line;
-            return;
+/// Sets package entry in `patch` section of a registry.
+fn set_patch(doc: &mut Document, registry: &str, package: &Package, mut entry: InlineTable) {
+    entry.fmt();
+    table_mut(doc, &["patch", registry]).insert(package.name().as_str(), Item::Value(entry.into()));
+}
+
+/// Moves `replace` section entries into `patch` section of a registry.
+/// Replaced version is kept as `version` key of a patch entry.
+/// Fails without changes if a package is replaced more than once
+/// or is already patched in the registry.
+fn migrate_replace(doc: &mut Document, registry: &str) -> Result<Vec<String>, failure::Error> {
+    let keys = match find_table_mut(doc, &["replace"]) {
+        Some(replace) => table_keys(replace),
+        None => return Ok(Vec::new()),
+    };
+    let patched = find_table_mut(doc, &["patch", registry])
+        .map(|patch| table_keys(patch))
+        .unwrap_or_default();
+    let mut migrated: Vec<String> = Vec::new();
+    for key in &keys {
+        // Replace keys are in `name:version` format
+        let name = key.splitn(2, ':').next().unwrap();
+        if migrated.iter().any(|other| other == name) {
+            return Err(format_err!("package {} is replaced more than once", name));
+        }
+        if patched.iter().any(|other| other == name) {
+            return Err(format_err!(
+                "package {} is already patched in patch.{}",
+                name,
+                registry
+            ));
+        }
+        migrated.push(name.to_owned());
+    }
+    let mut replace = match doc.as_table_mut().remove("replace") {
+        Some(Item::Table(replace)) => replace,
+        _ => return Ok(Vec::new()),
+    };
+    let patch = table_mut(doc, &["patch", registry]);
+    for key in &keys {
+        let mut parts = key.splitn(2, ':');
+        let name = parts.next().unwrap();
+        let mut item = replace.remove(key).unwrap();
+        if let Some(version) = parts.next() {
+            if get_str(&item, "version").is_none() {
+                set_str(&mut item, "version", version);
+            }
         }
+        trace!("Migrating replace {} to patch.{}", key, registry);
+        patch.insert(name, item);
     }
-    // if loop didnt return, insert new line
-    lines.push(new_line);
+    Ok(migrated)
 }
 
-/// Replaces first version in toml file.
-fn change_first_ver(lines: &mut Vec<String>, old: &Version, new: &Version) {
-    let ver_line = format!("version = \"{}\"", old);
-    for line in lines {
-        if *line == ver_line {
-            *line = format!("version = \"{}\"", new);
-            break;
-        }
+/// Sets package version and path in `replace` section.
+/// Existing path entries keep their formatting.
+fn set_replace(doc: &mut Document, package: &Package, path: &Path) {
+    let key = format!("{}:{}", package.name(), package.version());
+    let path = path.clean_path_str();
+    let find_name = format!("{}:", package.name());
+    let old = table_keys(table_mut(doc, &["replace"]))
+        .into_iter()
+        .find(|old| old.starts_with(&find_name));
+    if let Some(old) = old {
+        rename_key(doc, &["replace"], &old, &key);
+    }
+    let item = table_mut(doc, &["replace"]).entry(&key);
+    if item_keys(item) == ["path"] {
+        set_str(item, "path", &path);
+    } else {
+        let mut entry = InlineTable::default();
+        entry.get_or_insert("path", path);
+        *item = Item::Value(entry.into());
     }
 }
 
-/// Replaces dependency version in toml file.
-/// Works only for either `dependency = "x.x.x"`
-/// and `dependency = { version = "x.x.x" }`.
-fn change_dep_ver(lines: &mut Vec<String>, name: &str, old: &Version, new: &Version) {
-    let old = format!("{:?}", old.to_string());
-    let new = format!("{:?}", new.to_string());
-    let find_name = format!("{} ", name);
-    for line in lines {
-        if line.starts_with(&find_name) {
-            *line = line.replace(&old, &new);
+/// Removes package entries with a source key from `replace`
+/// and `patch` sections of all registries.
+fn remove_overrides(doc: &mut Document, package: &Package, source: &str) -> bool {
+    let mut removed = false;
+    if let Some(replace) = find_table_mut(doc, &["replace"]) {
+        let find_name = format!("{}:", package.name());
+        for key in table_keys(replace) {
+            let is_source = replace
+                .get(&key)
+                .map(|item| item_keys(item).iter().any(|k| k == source))
+                .unwrap_or(false);
+            if key.starts_with(&find_name) && is_source {
+                trace!("Removing replace {}", key);
+                replace.remove(&key);
+                removed = true;
+            }
         }
     }
+    if let Some(patch) = find_table_mut(doc, &["patch"]) {
+        for registry in table_keys(patch) {
+            let table = match patch.get_mut(&registry).and_then(Item::as_table_mut) {
+                Some(table) => table,
+                None => continue,
+            };
+            let is_source = table
+                .get(package.name().as_str())
+                .map(|item| item_keys(item).iter().any(|k| k == source))
+                .unwrap_or(false);
+            if is_source {
+                trace!("Removing patch.{} {}", registry, package.name());
+                table.remove(package.name().as_str());
+                removed = true;
+            }
+        }
+    }
+    removed
 }
 
-/// Replaces dependency version in toml file.
-/// Works only for either `dependency = "x.x.x"`
-/// and `dependency = { version = "x.x.x" }`.
-fn set_dep_force(lines: &mut Vec<String>, dep: &Dependency, pkg: &Package, path: Option<&Path>) {
-    let find_name = format!("{} ", dep.package_name().as_str());
-    for line in lines {
-        if line.starts_with(&find_name) {
-            *line = format!(
-                "{} = {}",
-                dep.package_name().as_str(),
-                format_pkg_dep(dep, pkg, path),
-            );
-        }
+/// Replaces version in a given table of toml file.
+fn change_table_ver(doc: &mut Document, table: &str, old: &Version, new: &Version) {
+    let keys: Vec<&str> = table.split('.').collect();
+    let item = match find_table_mut(doc, &keys).and_then(|table| table.get_mut("version")) {
+        Some(item) => item,
+        None => return,
+    };
+    if item.as_str() == Some(old.to_string().as_str()) {
+        set_item_str(item, &new.to_string());
     }
 }
 
-fn format_pkg_dep(dep: &Dependency, pkg: &Package, path: Option<&Path>) -> String {
+/// Updates dependency version requirement in toml file according to policy.
+/// Works for plain, inline table and `[dependencies.name]` dependencies
+/// in every dependency table of a manifest, including renamed ones.
+fn change_dep_ver(
+    doc: &mut Document,
+    name: &str,
+    old: &Version,
+    new: &Version,
+    policy: RequirementPolicy,
+) -> Result<(), failure::Error> {
+    let mut result = Ok(());
+    for_each_dep_table(doc, |table| {
+        for key in dep_keys(table, name) {
+            let item = table.get_mut(&key).unwrap();
+            let req = match dep_version(item) {
+                Some(req) => req.to_owned(),
+                None => continue,
+            };
+            match update_requirement(&req, old, new, policy) {
+                Ok(Some(updated)) => {
+                    trace!("Dependency {} requirement {:?} → {:?}", key, req, updated);
+                    set_dep_version(item, &updated);
+                }
+                Ok(None) => {}
+                Err(err) if result.is_ok() => {
+                    result = Err(format_err!("dependency {}: {}", key, err));
+                }
+                Err(_) => {}
+            }
+        }
+    });
+    result
+}
+
+/// Replaces dependency with version, path and options of a workspace package.
+/// Renamed dependencies keep their key and `package` name,
+/// table dependencies keep their form and formatting.
+fn set_dep_force(doc: &mut Document, dep: &Dependency, pkg: &Package, path: Option<&Path>) {
+    let key = dep.name_in_toml();
+    let version = pkg.version().to_string();
+    let path_str = path.map(|path| path.clean_path_str());
+    for_each_dep_table(doc, |table| {
+        if dep_keys(table, dep.package_name().as_str()).contains(&key.to_string()) {
+            let item = table.get_mut(key.as_str()).unwrap();
+            if !set_dep_source(item, &version, path_str.as_ref().map(String::as_str)) {
+                *item = Item::Value(format_pkg_dep(dep, pkg, path));
+            }
+        }
+    });
+}
+
+fn format_pkg_dep(dep: &Dependency, pkg: &Package, path: Option<&Path>) -> Value {
+    let is_renamed = dep.name_in_toml() != dep.package_name();
     if path.is_none()
+        && !is_renamed
         && !dep.is_optional()
         && dep.uses_default_features()
         && dep.features().len() == 0
     {
-        format!("{:?}", pkg.version().to_string())
+        pkg.version().to_string().as_str().into()
     } else {
-        let mut lines = Vec::new();
-        lines.push(format!("version = {:?}", pkg.version().to_string()));
+        let mut table = InlineTable::default();
+        if is_renamed {
+            table.get_or_insert("package", dep.package_name().as_str());
+        }
+        table.get_or_insert("version", pkg.version().to_string());
         if let Some(path) = path {
-            lines.push(format!("path = {:?}", path.clean_path()));
+            table.get_or_insert("path", path.clean_path_str());
         }
         if dep.is_optional() {
-            lines.push("optional = true".to_owned());
+            table.get_or_insert("optional", true);
         }
         if !dep.uses_default_features() {
-            lines.push("default-features = false".to_owned());
+            table.get_or_insert("default-features", false);
         }
         if dep.features().len() > 0 {
-            lines.push(format!(
-                "features = [{}]",
-                dep.features()
-                    .iter()
-                    .map(|f| format!("{:?}", f))
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            ));
+            let features: Value = dep.features().iter().map(|f| f.as_str()).collect();
+            table.get_or_insert("features", features);
         }
-
-        format!("{{ {} }}", lines.join(", "))
+        table.fmt();
+        table.into()
     }
 }
 
+/// Removes dependency path in toml file keeping its requirement and options.
+/// Path-only dependencies get a version, inline tables left with
+/// a version only are collapsed into a plain version.
+fn strip_dep_path(doc: &mut Document, name: &str, ver: &Version) {
+    for_each_dep_table(doc, |table| {
+        for key in dep_keys(table, name) {
+            let item = table.get_mut(&key).unwrap();
+            if get_str(item, "path").is_none() {
+                continue;
+            }
+            if dep_version(item).is_none() {
+                set_str(item, "version", &ver.to_string());
+            }
+            remove_key(item, "path");
+            let is_inline = match item {
+                Item::Value(Value::InlineTable(_)) => true,
+                _ => false,
+            };
+            if is_inline && item_keys(item) == ["version"] {
+                let version = dep_version(item).unwrap().to_owned();
+                set_item_str(item, &version);
+            }
+        }
+    });
+}
+
 /// Sets dependency path in toml file.
-/// Works only for `dependency = "x.x.x"`.
-fn set_dep_path(lines: &mut Vec<String>, name: &str, path: &Path, ver: &Version) {
-    let find_name = format!("{} = \"", name);
-    for line in lines {
-        if line.starts_with(&find_name) {
-            *line = format!(
-                "{} = {{ version = \"{}\", path = {:?} }}",
-                name,
-                ver,
-                path.clean_path()
-            );
+/// Plain version dependencies are converted into inline tables.
+fn set_dep_path(doc: &mut Document, name: &str, path: &Path, ver: &Version) {
+    for_each_dep_table(doc, |table| {
+        for key in dep_keys(table, name) {
+            let item = table.get_mut(&key).unwrap();
+            if item.as_str().is_some() {
+                let mut entry = InlineTable::default();
+                entry.get_or_insert("version", ver.to_string());
+                entry.get_or_insert("path", path.clean_path_str());
+                entry.fmt();
+                *item = Item::Value(entry.into());
+            } else if get_str(item, "path").is_none() {
+                set_str(item, "path", &path.clean_path_str());
+            }
         }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"[package]
+name = "b"
+version = "0.1.0" # package version
+
+[dependencies]
+# plain dependency
+a = "0.1.0" # keep
+# renamed dependency
+c = { package = "a", version = "=0.1.0", path = "../a" }
+
+[dependencies.d]
+# table dependency
+version = "~0.1" # keep
+path = "../d"
+features = ["x"]
+
+[target.'cfg(unix)'.dependencies]
+a = { version = "0.1", optional = true } # unix only
+
+[replace]
+# first "a:0.1.0" entry
+"a:0.1.0" = { path = "a" } # a
+# second
+"d:0.1.0" = { path = "d" } # d
+"#;
+
+    fn doc() -> Document {
+        MANIFEST.parse().unwrap()
+    }
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn change_dep_ver_keeps_format() {
+        let mut doc = doc();
+        change_dep_ver(
+            &mut doc,
+            "a",
+            &version("0.1.0"),
+            &version("0.2.0"),
+            RequirementPolicy::Preserve,
+        )
+        .unwrap();
+        let expected = MANIFEST
+            .replace("a = \"0.1.0\" # keep", "a = \"0.2.0\" # keep")
+            .replace("version = \"=0.1.0\"", "version = \"=0.2.0\"")
+            .replace("version = \"0.1\", optional", "version = \"0.2\", optional");
+        assert_eq!(doc.to_string(), expected);
+    }
+
+    #[test]
+    fn strip_dep_path_keeps_table_form() {
+        let mut doc = doc();
+        strip_dep_path(&mut doc, "d", &version("0.1.0"));
+        let expected = MANIFEST.replace("path = \"../d\"\n", "");
+        assert_eq!(doc.to_string(), expected);
+
+        strip_dep_path(&mut doc, "a", &version("0.1.0"));
+        let table = doc["dependencies"].as_table().unwrap();
+        assert_eq!(get_str(&table["c"], "path"), None);
+        assert_eq!(get_str(&table["c"], "package"), Some("a"));
+        assert_eq!(dep_version(&table["c"]), Some("=0.1.0"));
+    }
+
+    #[test]
+    fn set_dep_source_keeps_table_form() {
+        let mut doc = doc();
+        let table = find_table_mut(&mut doc, &["dependencies"]).unwrap();
+        assert!(set_dep_source(
+            table.get_mut("d").unwrap(),
+            "0.2.0",
+            Some("../../d")
+        ));
+        let expected = MANIFEST
+            .replace("version = \"~0.1\" # keep", "version = \"0.2.0\" # keep")
+            .replace("path = \"../d\"", "path = \"../../d\"");
+        assert_eq!(doc.to_string(), expected);
+
+        let table = find_table_mut(&mut doc, &["dependencies"]).unwrap();
+        assert!(set_dep_source(table.get_mut("d").unwrap(), "0.2.0", None));
+        assert!(!set_dep_source(table.get_mut("a").unwrap(), "0.2.0", None));
+        let expected = expected.replace("path = \"../../d\"\n", "");
+        assert_eq!(doc.to_string(), expected);
+    }
+
+    #[test]
+    fn migrate_replace_keeps_versions() {
+        let mut doc = doc();
+        let migrated = migrate_replace(&mut doc, "crates-io").unwrap();
+        assert_eq!(migrated, ["a", "d"]);
+        assert!(find_table_mut(&mut doc, &["replace"]).is_none());
+        let patch = find_table_mut(&mut doc, &["patch", "crates-io"]).unwrap();
+        assert_eq!(table_keys(patch), ["a", "d"]);
+        assert_eq!(get_str(&patch["a"], "path"), Some("a"));
+        assert_eq!(get_str(&patch["a"], "version"), Some("0.1.0"));
+        assert_eq!(get_str(&patch["d"], "version"), Some("0.1.0"));
+    }
+
+    #[test]
+    fn migrate_replace_conflicts() {
+        let patched = format!("{}\n[patch.crates-io]\nd = {{ path = \"d\" }}\n", MANIFEST);
+        let mut doc: Document = patched.parse().unwrap();
+        assert!(migrate_replace(&mut doc, "crates-io").is_err());
+        assert_eq!(doc.to_string(), patched);
+        assert!(migrate_replace(&mut doc, "other").is_ok());
+
+        let twice = MANIFEST.replace("\"d:0.1.0\"", "\"a:0.2.0\"");
+        let mut doc: Document = twice.parse().unwrap();
+        assert!(migrate_replace(&mut doc, "crates-io").is_err());
+        assert_eq!(doc.to_string(), twice);
+    }
+
+    #[test]
+    fn rename_key_keeps_order_and_comments() {
+        let mut doc = doc();
+        rename_key(&mut doc, &["replace"], "a:0.1.0", "a:0.2.0");
+        let replace = find_table_mut(&mut doc, &["replace"]).unwrap();
+        assert_eq!(table_keys(replace), ["a:0.2.0", "d:0.1.0"]);
+        assert_eq!(get_str(&replace["a:0.2.0"], "path"), Some("a"));
+        // Comment mentioning the key is left alone
+        let expected = MANIFEST.replace("\"a:0.1.0\" = ", "\"a:0.2.0\" = ");
+        assert_eq!(doc.to_string(), expected);
+        assert!(expected.contains("# first \"a:0.1.0\" entry\n\"a:0.2.0\""));
+    }
+
+    #[test]
+    fn rename_key_in_header() {
+        let manifest = "[replace]\n\n# first\n[replace.\"a:0.1.0\"]\npath = \"a\"\n";
+        let mut doc: Document = manifest.parse().unwrap();
+        rename_key(&mut doc, &["replace"], "a:0.1.0", "a:0.2.0");
+        assert_eq!(doc.to_string(), manifest.replace("0.1.0", "0.2.0"));
     }
 }
@@ -1,3 +1,4 @@
+mod document;
 mod manifest;
 mod package;
 mod workspace;
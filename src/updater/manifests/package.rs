@@ -1,9 +1,11 @@
 use std::path::{Path, PathBuf};
 
 use semver::Version;
+use toml_edit::Document;
 
 use crate::{
     git::Repository,
+    updater::RequirementPolicy,
     util::{Bump, BumpExt, CleanPath},
     ws::{Dependency, Package},
 };
@@ -16,6 +18,8 @@ pub struct PackageManifests<'a> {
     pub head: PackageManifest<'a>,
     pub index: PackageManifest<'a>,
     pub manifest_path: PathBuf,
+    /// Dependency version requirement update policy.
+    pub policy: RequirementPolicy,
 }
 
 impl<'a> PackageManifests<'a> {
@@ -36,6 +40,7 @@ impl<'a> PackageManifests<'a> {
             head,
             index,
             manifest_path,
+            policy: RequirementPolicy::default(),
         })
     }
 
@@ -52,7 +57,12 @@ impl<'a> PackageManifests<'a> {
     }
 
     /// Updates dependency version in manifest.
-    pub fn update_dep(&mut self, name: &str, old_ver: &semver::Version, new_ver: &semver::Version) {
+    pub fn update_dep(
+        &mut self,
+        name: &str,
+        old_ver: &semver::Version,
+        new_ver: &semver::Version,
+    ) -> Result<(), failure::Error> {
         trace!(
             "Updating package {} dependency {} version {} to {}",
             self.pkg.name(),
@@ -60,8 +70,8 @@ impl<'a> PackageManifests<'a> {
             old_ver,
             new_ver,
         );
-        self.head.update_dep(name, old_ver, new_ver);
-        self.index.update_dep(name, old_ver, new_ver);
+        self.head.update_dep(name, old_ver, new_ver, self.policy)?;
+        self.index.update_dep(name, old_ver, new_ver, self.policy)
     }
 
     /// Updates dependency version in manifest.
@@ -83,6 +93,27 @@ impl<'a> PackageManifests<'a> {
         self
     }
 
+    /// Removes dependency path keeping its requirement and options.
+    pub fn strip_dep_path(&mut self, name: &str, ver: &Version) -> &mut Self {
+        self.head.strip_dep_path(name, ver);
+        self.index.strip_dep_path(name, ver);
+        self
+    }
+
+    /// Removes dependency with a key from all dependency tables in manifest.
+    pub fn remove_dep(&mut self, key: &str) -> &mut Self {
+        self.head.remove_dep(key);
+        self.index.remove_dep(key);
+        self
+    }
+
+    /// Adds dependency with version and path to manifest.
+    pub fn add_dep(&mut self, name: &str, ver: &Version, path: &Path, dev: bool) -> &mut Self {
+        self.head.add_dep(name, ver, path, dev);
+        self.index.add_dep(name, ver, path, dev);
+        self
+    }
+
     /// Creates a head preview path.
     pub fn head_preview_path(&self) -> PathBuf {
         self.manifest_path
@@ -112,21 +143,19 @@ impl<'a> PackageManifests<'a> {
 #[derive(Clone)]
 pub struct PackageManifest<'a> {
     pub pkg: &'a Package<'a>,
-    pub lines: Vec<String>,
+    pub document: Document,
 }
 
 impl<'a> PackageManifest<'a> {
     pub fn new_head(repo: &mut Repository, pkg: &'a Package<'a>) -> Result<Self, failure::Error> {
         let content = repo.get_contents(&repo.head_tree()?, pkg.manifest_path())?;
-        let content = String::from_utf8(content)?;
-        let lines: Vec<String> = content.lines().map(|line| line.to_owned()).collect();
-        Ok(PackageManifest { pkg, lines })
+        let document = String::from_utf8(content)?.parse()?;
+        Ok(PackageManifest { pkg, document })
     }
 
-    pub fn new_index(pkg: &'a Package) -> std::io::Result<Self> {
-        let content = std::fs::read_to_string(pkg.manifest_path())?;
-        let lines: Vec<String> = content.lines().map(|line| line.to_owned()).collect();
-        Ok(PackageManifest { pkg, lines })
+    pub fn new_index(pkg: &'a Package) -> Result<Self, failure::Error> {
+        let document = std::fs::read_to_string(pkg.manifest_path())?.parse()?;
+        Ok(PackageManifest { pkg, document })
     }
 }
 
@@ -137,11 +166,11 @@ impl PackageManifestExt for PackageManifest<'_> {
 }
 
 impl ManifestExt for PackageManifest<'_> {
-    fn lines(&self) -> &Vec<String> {
-        &self.lines
+    fn document(&self) -> &Document {
+        &self.document
     }
 
-    fn lines_mut(&mut self) -> &mut Vec<String> {
-        &mut self.lines
+    fn document_mut(&mut self) -> &mut Document {
+        &mut self.document
     }
 }
@@ -1,5 +1,7 @@
 use std::path::{Path, PathBuf};
 
+use toml_edit::Document;
+
 use crate::git::Repository;
 use crate::util::{Bump, CleanPath};
 use crate::ws::Package;
@@ -57,40 +59,62 @@ impl WorkspaceManifests {
         self.index.bump_replace_ver(package, bump);
     }
 
+    /// Bumps workspace version in a manifest table.
+    pub fn bump_workspace_ver(&mut self, table: &str, version: &semver::Version, bump: Bump) {
+        self.head.bump_workspace_ver(table, version, bump);
+        self.index.bump_workspace_ver(table, version, bump);
+    }
+
     /// Bumps package version in manifest.
     pub fn set_replace(&mut self, package: &Package, path: &Path) {
         self.head.set_replace(package, path);
         self.index.set_replace(package, path);
     }
+
+    /// Sets package path in `patch` section of a registry.
+    pub fn set_patch(&mut self, registry: &str, package: &Package, path: &Path) {
+        self.head.set_patch(registry, package, path);
+        self.index.set_patch(registry, package, path);
+    }
+
+    /// Removes package `replace` and `patch` entries with a source key.
+    pub fn remove_overrides(&mut self, package: &Package, source: &str) -> bool {
+        self.head.remove_overrides(package, source);
+        self.index.remove_overrides(package, source)
+    }
+
+    /// Moves `replace` section entries into `patch` section of a registry.
+    pub fn migrate_replace(&mut self, registry: &str) -> Result<Vec<String>, failure::Error> {
+        self.head.migrate_replace(registry)?;
+        self.index.migrate_replace(registry)
+    }
 }
 
 /// Package manifest.
 pub struct WorkspaceManifest {
-    pub lines: Vec<String>,
+    pub document: Document,
 }
 
 impl WorkspaceManifest {
-    pub fn new_head(repo: &mut Repository) -> Result<Self, git2::Error> {
+    pub fn new_head(repo: &mut Repository) -> Result<Self, failure::Error> {
         let content = repo.get_contents(&repo.head_tree()?, Path::new("Cargo.toml"))?;
-        let content = String::from_utf8(content).unwrap();
-        let lines: Vec<String> = content.lines().map(|line| line.to_owned()).collect();
-        Ok(WorkspaceManifest { lines })
+        let document = String::from_utf8(content)?.parse()?;
+        Ok(WorkspaceManifest { document })
     }
 
-    pub fn new_index<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let lines: Vec<String> = content.lines().map(|line| line.to_owned()).collect();
-        Ok(WorkspaceManifest { lines })
+    pub fn new_index<P: AsRef<Path>>(path: P) -> Result<Self, failure::Error> {
+        let document = std::fs::read_to_string(path)?.parse()?;
+        Ok(WorkspaceManifest { document })
     }
 }
 
 impl ManifestExt for WorkspaceManifest {
-    fn lines(&self) -> &Vec<String> {
-        &self.lines
+    fn document(&self) -> &Document {
+        &self.document
     }
 
-    fn lines_mut(&mut self) -> &mut Vec<String> {
-        &mut self.lines
+    fn document_mut(&mut self) -> &mut Document {
+        &mut self.document
     }
 }
 
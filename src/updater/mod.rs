@@ -1,5 +1,7 @@
 mod manifests;
+mod requirement;
 pub use self::manifests::*;
+pub use self::requirement::*;
 
 use hashbrown::HashMap;
 
@@ -12,6 +14,10 @@ use crate::{
 pub struct Updater<'a> {
     pub workspace: WorkspaceManifests,
     pub toml_files: HashMap<String, PackageManifests<'a>>,
+    /// Registry to patch instead of using `replace` section.
+    pub patch_registry: Option<String>,
+    /// Dependency version requirement update policy.
+    pub policy: RequirementPolicy,
 }
 
 impl<'a> Updater<'a> {
@@ -19,6 +25,8 @@ impl<'a> Updater<'a> {
         Ok(Updater {
             workspace: WorkspaceManifests::new(repo)?,
             toml_files: HashMap::default(),
+            patch_registry: None,
+            policy: RequirementPolicy::default(),
         })
     }
 
@@ -68,6 +76,51 @@ impl<'a> Updater<'a> {
         Ok(())
     }
 
+    /// Replaces path dependencies on members of a source workspace
+    /// with version dependencies and removes their path overrides.
+    pub fn strip_paths(
+        &mut self,
+        repo: &mut Repository,
+        workspace: &'a Workspace<'a>,
+        source: &Workspace,
+        dry_run: bool,
+    ) -> Result<(), failure::Error> {
+        for pkg in workspace.packages().iter() {
+            let packages: Vec<_> = pkg
+                .dependencies()
+                .iter()
+                .filter_map(|dep| {
+                    source
+                        .find_package(dep.package_name().as_str())
+                        .map(|dep_pkg| (dep, dep_pkg))
+                })
+                .collect();
+            if packages.len() == 0 {
+                trace!("No members in package {}.", pkg.name());
+                continue;
+            }
+            let manifests = self.manifests(pkg, repo)?;
+            for (dep, dep_pkg) in &packages {
+                trace!(
+                    "Removing package {} dependency {} path",
+                    pkg.name(),
+                    dep_pkg.name()
+                );
+                manifests.strip_dep_path(dep.package_name().as_str(), dep_pkg.version());
+            }
+            trace!("Saving package {} versions.", pkg.name());
+            manifests.save_preview()?;
+            if !dry_run {
+                util::rename(manifests.index_preview_path(), &manifests.manifest_path)?;
+                util::commit::remove_previews(&manifests.manifest_path)?;
+            }
+            for (_, dep_pkg) in packages {
+                self.workspace.remove_overrides(dep_pkg, "path");
+            }
+        }
+        Ok(())
+    }
+
     fn set_pkg_paths_impl(
         &mut self,
         pkg: &'a Package,
@@ -95,12 +148,9 @@ impl<'a> Updater<'a> {
         for (dep, dep_pkg) in packages {
             let manifests = self.manifests(pkg, repo)?;
             let name = dep_pkg.name().as_str();
-            trace!(
-                "Dependency {} manifest_path: {:?}",
-                name,
-                dep_pkg.directory()
-            );
-            let dep_path = repo.rel_path(dep_pkg.directory());
+            trace!("Dependency {} root: {:?}", name, dep_pkg.root());
+            // Submodule packages directories are relative to their own repository
+            let dep_path = repo.rel_path(&dep_pkg.root().fix_path());
             trace!("Dependency {} path: {:?}", name, dep_path);
             let rel_path = pathdiff::diff_paths(&dep_path, &pkg_path)
                 .unwrap()
@@ -111,7 +161,10 @@ impl<'a> Updater<'a> {
                     dep_pkg,
                     if is_submodule { None } else { Some(&rel_path) },
                 );
-                self.workspace.set_replace(dep_pkg, &dep_path);
+                match &self.patch_registry {
+                    Some(registry) => self.workspace.set_patch(registry, dep_pkg, &dep_path),
+                    None => self.workspace.set_replace(dep_pkg, &dep_path),
+                }
             } else {
                 manifests.set_dep_path(name, &rel_path, dep_pkg.version());
             }
@@ -122,6 +175,7 @@ impl<'a> Updater<'a> {
         manifests.save_preview().unwrap();
         if !dry_run {
             util::rename(manifests.index_preview_path(), &manifests.manifest_path)?;
+            util::commit::remove_previews(&manifests.manifest_path)?;
         }
         Ok(())
     }
@@ -133,7 +187,8 @@ impl<'a> Updater<'a> {
     ) -> Result<&mut PackageManifests<'a>, failure::Error> {
         let name = pkg.name().as_str();
         if !self.toml_files.contains_key(name) {
-            let toml_file = PackageManifests::new(repo, pkg)?;
+            let mut toml_file = PackageManifests::new(repo, pkg)?;
+            toml_file.policy = self.policy;
             self.toml_files.insert(name.to_owned(), toml_file);
         }
         Ok(self.toml_files.get_mut(name).unwrap())
@@ -0,0 +1,165 @@
+//! Dependency version requirement updates.
+
+use failure::Error;
+use semver::{Version, VersionReq};
+
+/// Policy of updating dependency version requirements.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequirementPolicy {
+    /// Keeps requirement operators and precision, updates versions
+    /// (`=1.2.3` → `=1.3.0`, `~1.2` → `~1.3`).
+    Preserve,
+    /// Pins requirement to the new version (`=1.3.0`).
+    Exact,
+    /// Replaces requirement with minimal caret requirement (`1.3.0`).
+    Caret,
+    /// Leaves requirement alone while it is still compatible
+    /// with the new version, otherwise updates it as `Preserve`.
+    Compatible,
+}
+
+impl Default for RequirementPolicy {
+    fn default() -> Self {
+        RequirementPolicy::Preserve
+    }
+}
+
+/// Comparison operators in order of matching.
+static OPERATORS: &[&str] = &[">=", "<=", "=", "^", "~", ">", "<"];
+
+/// Updates version requirement of a dependency from old to new version.
+/// Returns `None` if requirement doesn't need to change or doesn't
+/// refer to the old version, and an error if updated requirement
+/// doesn't match the new version, e.g. `>=1.2, <2` for `2.0.0`.
+pub fn update_requirement(
+    req: &str,
+    old: &Version,
+    new: &Version,
+    policy: RequirementPolicy,
+) -> Result<Option<String>, Error> {
+    let parsed = match VersionReq::parse(req) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            warn!("Invalid version requirement {:?}: {}", req, err);
+            return Ok(None);
+        }
+    };
+    if !parsed.matches(old) {
+        trace!("Requirement {:?} doesn't match version {}", req, old);
+        return Ok(None);
+    }
+    let updated = match policy {
+        RequirementPolicy::Exact => format!("={}", new),
+        RequirementPolicy::Caret => new.to_string(),
+        RequirementPolicy::Compatible if parsed.matches(new) => return Ok(None),
+        RequirementPolicy::Preserve | RequirementPolicy::Compatible => req
+            .split(',')
+            .map(|comparator| update_comparator(comparator.trim(), new))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    match VersionReq::parse(&updated) {
+        Ok(ref parsed) if parsed.matches(new) => {}
+        _ => {
+            return Err(format_err!(
+                "requirement {:?} can't be updated to {}, {:?} doesn't match it",
+                req,
+                new,
+                updated
+            ))
+        }
+    }
+    if updated == req {
+        Ok(None)
+    } else {
+        Ok(Some(updated))
+    }
+}
+
+/// Updates version of a single comparator keeping its operator and precision.
+/// Upper bounds and wildcards are left alone.
+fn update_comparator(comparator: &str, new: &Version) -> String {
+    let op = OPERATORS
+        .iter()
+        .find(|op| comparator.starts_with(*op))
+        .map(|op| *op)
+        .unwrap_or("");
+    let version = comparator[op.len()..].trim();
+    let parts: Vec<&str> = version
+        .split(|c| c == '-' || c == '+')
+        .next()
+        .unwrap()
+        .split('.')
+        .collect();
+    let is_wildcard = parts.iter().any(|part| ["*", "x", "X"].contains(part));
+    if op == "<" || op == "<=" || is_wildcard {
+        return comparator.to_owned();
+    }
+    let version = match parts.len() {
+        1 => new.major.to_string(),
+        2 => format!("{}.{}", new.major, new.minor),
+        _ => new.to_string(),
+    };
+    format!("{}{}", op, version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use RequirementPolicy::*;
+
+    fn update(req: &str, old: &str, new: &str, policy: RequirementPolicy) -> Option<String> {
+        let old = Version::parse(old).unwrap();
+        let new = Version::parse(new).unwrap();
+        update_requirement(req, &old, &new, policy).unwrap()
+    }
+
+    #[test]
+    fn update_requirements() {
+        let cases: &[(&str, &str, &str, RequirementPolicy, Option<&str>)] = &[
+            ("1.2.3", "1.2.3", "1.3.0", Preserve, Some("1.3.0")),
+            ("1.2", "1.2.3", "1.3.0", Preserve, Some("1.3")),
+            ("^1", "1.2.3", "1.3.0", Preserve, None),
+            ("^1", "1.2.3", "2.0.0", Preserve, Some("^2")),
+            ("=1.2.3", "1.2.3", "1.2.4", Preserve, Some("=1.2.4")),
+            ("~1.2", "1.2.3", "1.3.0", Preserve, Some("~1.3")),
+            (">=1.2, <2", "1.2.3", "1.3.0", Preserve, Some(">=1.3, <2")),
+            ("1.*", "1.2.3", "1.3.0", Preserve, None),
+            ("1.2.3", "1.2.3", "1.3.0", Exact, Some("=1.3.0")),
+            ("~1.2", "1.2.3", "1.3.0", Exact, Some("=1.3.0")),
+            ("=1.2.3", "1.2.3", "1.3.0", Caret, Some("1.3.0")),
+            (">=1.2, <2", "1.2.3", "1.3.0", Caret, Some("1.3.0")),
+            ("1.2", "1.2.3", "1.3.0", Compatible, None),
+            (">=1.2, <2", "1.2.3", "1.3.0", Compatible, None),
+            ("~1.2", "1.2.3", "1.3.0", Compatible, Some("~1.3")),
+            ("=1.2.3", "1.2.3", "1.2.4", Compatible, Some("=1.2.4")),
+            ("2.0", "1.2.3", "1.3.0", Preserve, None),
+            ("not a requirement", "1.2.3", "1.3.0", Preserve, None),
+        ];
+        for (req, old, new, policy, expected) in cases {
+            assert_eq!(
+                update(req, old, new, *policy).as_ref().map(String::as_str),
+                *expected,
+                "{:?} {} → {} with {:?}",
+                req,
+                old,
+                new,
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn bounded_requirement_error() {
+        let old = Version::parse("1.2.3").unwrap();
+        let new = Version::parse("2.0.0").unwrap();
+        assert!(update_requirement(">=1.2, <2", &old, &new, Preserve).is_err());
+        assert!(update_requirement(">=1.2, <2", &old, &new, Compatible).is_err());
+        assert_eq!(
+            update_requirement(">=1.2, <2", &old, &new, Caret).unwrap(),
+            Some("2.0.0".to_owned())
+        );
+    }
+}
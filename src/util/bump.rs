@@ -10,7 +10,7 @@ pub static UPDATES: &'static [Update] = &[
 ];
 
 /// Kind of update.
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 pub enum Update {
     Docs,
     Chore,
@@ -20,6 +20,17 @@ pub enum Update {
 }
 
 impl Update {
+    /// Returns update kind name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Update::Docs => "docs",
+            Update::Chore => "chore",
+            Update::Patch => "patch",
+            Update::Minor => "minor",
+            Update::Major => "major",
+        }
+    }
+
     /// Returns update commit type.
     pub fn commit_type(&self) -> &'static str {
         match self {
@@ -72,6 +83,18 @@ impl Update {
     }
 }
 
+impl std::str::FromStr for Update {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UPDATES
+            .iter()
+            .find(|update| update.name() == s)
+            .cloned()
+            .ok_or_else(|| format_err!("unknown update kind: {}", s))
+    }
+}
+
 /// Kind of a semver bump.
 #[derive(Copy, Clone)]
 pub enum Bump {
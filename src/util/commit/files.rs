@@ -1,7 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use crate::{
-    git::{CommitBuilder, Diff},
+    git::{CommitBuilder, DeltaKind, Diff},
     util::{self, CleanPath},
 };
 
@@ -13,6 +13,27 @@ pub fn restore_manifest<P: AsRef<Path>>(manifest_path: P) -> Result<(), failure:
     Ok(())
 }
 
+/// Restores backed up cargo manifests, skipping ones without a backup.
+pub fn restore_manifests<P: AsRef<Path>>(manifest_paths: &[P]) -> Result<(), failure::Error> {
+    for manifest_path in manifest_paths {
+        let source_dir = manifest_path.as_ref().parent().unwrap();
+        if source_dir.join("Cargo.backup.toml").exists() {
+            restore_manifest(manifest_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes backed up cargo manifest once release is committed.
+pub fn remove_backup<P: AsRef<Path>>(manifest_path: P) -> Result<(), failure::Error> {
+    let source_dir = manifest_path.as_ref().parent().unwrap();
+    let backup_toml = source_dir.join("Cargo.backup.toml");
+    if backup_toml.exists() {
+        util::remove_file(backup_toml)?;
+    }
+    Ok(())
+}
+
 /// Moves preview cargo manifest from index as default manifest.
 pub fn move_index_manifest<P: AsRef<Path>>(manifest_path: P) -> Result<(), failure::Error> {
     let source_dir = manifest_path.as_ref().parent().unwrap();
@@ -23,6 +44,18 @@ pub fn move_index_manifest<P: AsRef<Path>>(manifest_path: P) -> Result<(), failu
     Ok(())
 }
 
+/// Removes preview cargo manifests left next to a manifest.
+pub fn remove_previews<P: AsRef<Path>>(manifest_path: P) -> Result<(), failure::Error> {
+    let source_dir = manifest_path.as_ref().parent().unwrap();
+    for kind in &["head", "index"] {
+        let preview = source_dir.join(format!("Cargo.preview-{}.toml", kind));
+        if preview.exists() {
+            util::remove_file(preview)?;
+        }
+    }
+    Ok(())
+}
+
 /// Adds preview cargo manifest from HEAD to commit.
 pub fn add_preview_head<P: AsRef<Path>>(
     commit: &mut CommitBuilder,
@@ -60,40 +93,41 @@ pub fn add_diff(
     diff: &Diff,
     dir: &PathBuf,
 ) -> Result<(), failure::Error> {
-    add_files(commit, &diff.changed_files, dir)?;
-    remove_files(commit, &diff.deleted_files, dir)
+    for delta in &diff.deltas {
+        match delta.kind {
+            DeltaKind::Added | DeltaKind::Modified => add_file(commit, &delta.path, dir)?,
+            DeltaKind::Deleted => remove_file(commit, &delta.path, dir)?,
+            DeltaKind::Renamed => {
+                if let Some(old_path) = &delta.old_path {
+                    remove_file(commit, old_path, dir)?;
+                }
+                add_file(commit, &delta.path, dir)?;
+            }
+        }
+    }
+    Ok(())
 }
 
-/// Removes files in commit.
-pub fn remove_files(
-    commit: &mut CommitBuilder,
-    files: &Vec<PathBuf>,
-    dir: &PathBuf,
-) -> Result<(), failure::Error> {
-    for file in files {
-        // Add removed file to commit
-        commit.add_path(&file.clean_path())?;
-        // Remove file in cached repo
-        let dest = dir.join(&file);
+/// Removes file in commit.
+fn remove_file(commit: &mut CommitBuilder, file: &Path, dir: &Path) -> Result<(), failure::Error> {
+    // Remove file from commit
+    commit.remove_path(&file.clean_path())?;
+    // Remove file in cached repo
+    let dest = dir.join(&file);
+    if dest.exists() {
         util::remove_file(dest)?;
     }
     Ok(())
 }
 
-/// Adds files in commit.
-pub fn add_files(
-    commit: &mut CommitBuilder,
-    files: &Vec<PathBuf>,
-    dir: &PathBuf,
-) -> Result<(), failure::Error> {
-    for file in files {
-        // Before copying create dir
-        util::ensure_dir_exists(&file)?;
-        // Add changed file to commit
-        commit.add_path(&file.clean_path())?;
-        // Copy changed file to cached repo
-        let dest = dir.join(&file);
-        util::copy(&file, dest)?;
-    }
+/// Adds file in commit.
+fn add_file(commit: &mut CommitBuilder, file: &Path, dir: &Path) -> Result<(), failure::Error> {
+    // Before copying create dir
+    util::ensure_dir_exists(&file)?;
+    // Add changed file to commit
+    commit.add_path(&file.clean_path())?;
+    // Copy changed file to cached repo
+    let dest = dir.join(&file);
+    util::copy(&file, dest)?;
     Ok(())
 }
@@ -1,51 +1,220 @@
-use crate::util::Update;
+use glob::Pattern;
+
+use crate::util::{BumpExt, Update};
 use crate::ws::Package;
 
+/// Commit messages configuration.
+///
+/// Templates placeholders: `{type}`, `{scope}`, `{description}`, `{name}`,
+/// `{update}`, `{old_version}`, `{new_version}`, `{transition}`, `{header}`,
+/// `{body}` and `{dependants}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+pub struct MessageConfig {
+    /// Package update commit message template.
+    pub package_template: String,
+    /// Dependants update commit message template.
+    pub dependants_template: String,
+    /// Commit scope rules, first matching rule is used.
+    pub scopes: Vec<ScopeRule>,
+    /// Minimal commit header length.
+    pub header_min: usize,
+    /// Maximal commit header length.
+    pub header_max: usize,
+}
+
+impl Default for MessageConfig {
+    fn default() -> Self {
+        MessageConfig {
+            package_template:
+                "{type}({scope}): {description} of {name} {transition} ({header})\n\n{body}"
+                    .to_owned(),
+            dependants_template:
+                "{type}(*): {description} of {name} {transition} ({header})\n\n{body}".to_owned(),
+            scopes: Vec::new(),
+            header_min: 3,
+            header_max: 22,
+        }
+    }
+}
+
+/// Commit scope rule.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScopeRule {
+    /// Package name glob pattern, e.g. `wtf-*`.
+    pub pattern: String,
+    /// Scope template, `{name}` is replaced with package name.
+    pub scope: String,
+}
+
+impl MessageConfig {
+    /// Checks header limits and scope patterns.
+    pub fn validate(&self) -> Result<(), failure::Error> {
+        if self.header_min > self.header_max {
+            return Err(format_err!(
+                "messages: header_min {} is greater than header_max {}",
+                self.header_min,
+                self.header_max
+            ));
+        }
+        for rule in &self.scopes {
+            Pattern::new(&rule.pattern).map_err(|err| {
+                format_err!(
+                    "messages: invalid scope pattern {:?}: {}",
+                    rule.pattern,
+                    err
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Returns commit scope of a package.
+    /// Defaults to package name with first `-` replaced by `/`.
+    pub fn scope(&self, name: &str) -> String {
+        let rule = self
+            .scopes
+            .iter()
+            .find(|rule| match Pattern::new(&rule.pattern) {
+                Ok(pattern) => pattern.matches(name),
+                Err(err) => {
+                    warn!("Invalid scope pattern {:?}: {}", rule.pattern, err);
+                    false
+                }
+            });
+        match rule {
+            Some(rule) => rule.scope.replace("{name}", name),
+            None => name.replacen("-", "/", 1),
+        }
+    }
+}
+
 /// Creates a commit message.
 pub fn message(
+    config: &MessageConfig,
     package: &Package,
     update: &Update,
     header: &str,
     extra: Option<&Vec<String>>,
+    dependants: &[&Package],
     is_dep: bool,
 ) -> String {
-    let dep_commit = if is_dep {
-        dep_update(package, update, header)
+    let template = if is_dep {
+        &config.dependants_template
     } else {
-        pkg_update(package, update, header)
+        &config.package_template
     };
-    let mut commit_lines = vec![dep_commit];
-    if let Some(extra) = extra {
-        commit_lines.push("".to_owned());
-        commit_lines.extend(extra.clone());
-    }
-    for line in &commit_lines {
-        trace!("Dependency commit message: {}", line);
+    let name = package.name().to_string();
+    let new_version = match update.as_bump() {
+        Some(bump) => package.version().bump(bump),
+        None => package.version().clone(),
+    };
+    let dependants: Vec<String> = dependants
+        .iter()
+        .map(|dep| dep.name().to_string())
+        .collect();
+    let body = extra.map(|lines| lines.join("\n")).unwrap_or_default();
+    let vars = &[
+        ("type", update.commit_type().to_owned()),
+        ("scope", config.scope(&name)),
+        ("description", update.commit_description().to_owned()),
+        ("update", update.name().to_owned()),
+        ("old_version", package.version().to_string()),
+        ("new_version", new_version.to_string()),
+        ("transition", update.transition(package.version())),
+        ("header", header.to_owned()),
+        ("body", body.trim().to_owned()),
+        ("dependants", dependants.join(", ")),
+        ("name", name),
+    ];
+    let message = render(template, vars);
+    for line in message.lines() {
+        trace!("Commit message: {}", line);
     }
-    commit_lines.join("\n")
+    message.trim().to_owned()
 }
 
-/// Creates a package update commit message.
-fn pkg_update(package: &Package, update: &Update, header: &str) -> String {
-    format!(
-        "{}({}): {} of {} {} ({})",
-        update.commit_type(),
-        package.name().to_string().replacen("-", "/", 1),
-        update.commit_description(),
-        package.name(),
-        update.transition(package.version()),
-        header
-    )
+/// Replaces `{key}` placeholders in a template in a single pass,
+/// placeholders in substituted values and unknown ones are kept as is.
+fn render(template: &str, vars: &[(&str, String)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            vars.iter()
+                .find(|(key, _)| *key == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                result.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
 }
 
-/// Creates a dependency update commit message.
-fn dep_update(package: &Package, update: &Update, header: &str) -> String {
-    format!(
-        "{}(*): {} of {} {} ({})",
-        update.commit_type(),
-        update.commit_description(),
-        package.name(),
-        update.transition(package.version()),
-        header
-    )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Vec<(&'static str, String)> {
+        vec![
+            ("name", "wtf-core".to_owned()),
+            ("header", "use {name} in {scope}".to_owned()),
+        ]
+    }
+
+    #[test]
+    fn render_placeholders() {
+        assert_eq!(
+            render("{name}: {header}", &vars()),
+            "wtf-core: use {name} in {scope}"
+        );
+        assert_eq!(render("{unknown} {name}", &vars()), "{unknown} wtf-core");
+        assert_eq!(render("{{name}} {", &vars()), "{wtf-core} {");
+        assert_eq!(render("no placeholders", &vars()), "no placeholders");
+    }
+
+    #[test]
+    fn scope_rules() {
+        let mut config = MessageConfig::default();
+        assert_eq!(config.scope("wtf-core-api"), "wtf/core-api");
+        assert_eq!(config.scope("core"), "core");
+        config.scopes = vec![
+            ScopeRule {
+                pattern: "wtf-core*".to_owned(),
+                scope: "core:{name}".to_owned(),
+            },
+            ScopeRule {
+                pattern: "wtf-*".to_owned(),
+                scope: "wtf".to_owned(),
+            },
+        ];
+        assert_eq!(config.scope("wtf-core-api"), "core:wtf-core-api");
+        assert_eq!(config.scope("wtf-ui"), "wtf");
+        assert_eq!(config.scope("other-ui"), "other/ui");
+    }
+
+    #[test]
+    fn validate_config() {
+        assert!(MessageConfig::default().validate().is_ok());
+        let mut config = MessageConfig::default();
+        config.header_min = 30;
+        assert!(config.validate().is_err());
+        let mut config = MessageConfig::default();
+        config.scopes.push(ScopeRule {
+            pattern: "wtf-[".to_owned(),
+            scope: "wtf".to_owned(),
+        });
+        assert!(config.validate().is_err());
+    }
 }
@@ -0,0 +1,48 @@
+//! Wtf-rlsr workspace configuration.
+
+use std::path::PathBuf;
+
+use crate::{
+    git::{ChangeDetection, CommitConfig, CredentialsConfig, MergeStrategy, SigningConfig},
+    updater::RequirementPolicy,
+    util::commit::MessageConfig,
+};
+
+/// Wtf-rlsr workspace configuration structure.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+pub struct Config {
+    /// Cache directory.
+    pub cache: Option<PathBuf>,
+    /// Dependency version requirement update policy.
+    pub requirement_policy: RequirementPolicy,
+    /// Package change detection mode.
+    pub change_detection: ChangeDetection,
+    /// Cache repository merge conflict strategy.
+    pub merge_strategy: MergeStrategy,
+    /// Git remote credentials.
+    pub credentials: CredentialsConfig,
+    /// Commit message templates.
+    pub messages: MessageConfig,
+    /// Commit identity and trailers.
+    pub commit: CommitConfig,
+    /// Commit signing overrides of git config.
+    pub signing: SigningConfig,
+    /// `cfg` predicates of modules skipped when parsing sources.
+    pub disabled_cfgs: Vec<String>,
+}
+
+impl Config {
+    /// Reads `.wtf-rlsr.json` configuration from current directory.
+    pub fn read() -> Result<Option<Config>, failure::Error> {
+        let cfg_path: PathBuf = ".wtf-rlsr.json".into();
+        if cfg_path.exists() {
+            let contents = std::fs::read_to_string(&cfg_path)?;
+            let config: Config = serde_json::from_str(&contents)?;
+            config.messages.validate()?;
+            Ok(Some(config))
+        } else {
+            Ok(None)
+        }
+    }
+}
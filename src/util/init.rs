@@ -1,20 +1,21 @@
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use structopt::StructOpt;
 
-use crate::{cmd::Opt, util::CleanPath};
-
-/// Wtf-rlsr workspace configuration structure.
-#[derive(Deserialize)]
-struct Config {
-    cache: PathBuf,
-}
+use crate::{
+    cmd::Opt,
+    git::ChangeDetection,
+    util::{CleanPath, Config},
+};
 
 impl Config {
     fn merge_into(self, opt: &mut Opt) {
         if opt.cache_dir.to_str().unwrap() == "../cache" {
-            opt.cache_dir = self.cache;
+            if let Some(cache) = &self.cache {
+                opt.cache_dir = cache.clone();
+            }
         }
+        opt.config = self;
     }
 }
 
@@ -23,9 +24,20 @@ pub fn setup_opt() -> Result<Opt, failure::Error> {
     pretty_env_logger::init();
     cargo::core::features::enable_nightly_features();
     let mut opt = Opt::from_args();
-    if let Some(config) = read_config()? {
+    if let Some(config) = Config::read()? {
         config.merge_into(&mut opt);
     }
+    if opt.since_release {
+        opt.config.change_detection = ChangeDetection::SinceRelease;
+    }
+    if let Some(strategy) = opt.merge_strategy {
+        opt.config.merge_strategy = strategy;
+    }
+    // Answers files are used after changing working directory
+    let cwd = std::env::current_dir()?;
+    for path in opt.answers.iter_mut().chain(opt.record_answers.iter_mut()) {
+        *path = cwd.join(&path);
+    }
     if opt.directory.to_str().unwrap() == "." {
         opt.directory = std::env::current_dir()?.fix_path();
     } else {
@@ -50,14 +62,3 @@ pub fn set_cwd<P: AsRef<Path>>(dir: P) -> std::io::Result<()> {
     trace!("Current working directory: {:?}", std::env::current_dir()?);
     Ok(())
 }
-
-fn read_config() -> Result<Option<Config>, failure::Error> {
-    let cfg_path: PathBuf = ".wtf-rlsr.json".into();
-    if cfg_path.exists() {
-        let contents = std::fs::read_to_string(&cfg_path)?;
-        let config = serde_json::from_str(&contents)?;
-        Ok(Some(config))
-    } else {
-        Ok(None)
-    }
-}
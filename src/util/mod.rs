@@ -1,16 +1,24 @@
 mod bump;
 pub mod commit;
+mod config;
 pub mod diff2html;
 mod files;
 pub mod init;
 mod logger;
 mod paths;
+mod preflight;
 mod publisher;
+mod registry;
+pub mod report;
 mod testing;
 
 pub use self::bump::*;
+pub use self::config::*;
 pub use self::files::*;
 pub use self::logger::*;
 pub use self::paths::*;
+pub use self::preflight::*;
 pub use self::publisher::*;
+pub use self::registry::*;
+pub use self::report::{Report, ReportFormat};
 pub use self::testing::*;
@@ -5,13 +5,80 @@ use std::path::{Path, PathBuf};
 use cargo::core::Package;
 use glob::glob;
 
-/// Creates a source code git diff paths for package root.
-pub fn source_git_diff_paths(pkg: &Package) -> Vec<&Path> {
-    pkg.targets()
-        .iter()
-        .filter_map(|target| target.src_path().path())
-        .map(|path| path.parent().unwrap())
-        .collect()
+/// Package change detection settings in `[package.metadata.wtf-rlsr]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PackageMetadata {
+    /// Extra globs of files which changes are detected.
+    pub include: Vec<String>,
+    /// Globs of files which changes are ignored.
+    pub ignore: Vec<String>,
+}
+
+impl PackageMetadata {
+    /// Reads change detection settings from package metadata.
+    pub fn read(pkg: &Package) -> Self {
+        let metadata = match pkg
+            .manifest()
+            .custom_metadata()
+            .and_then(|metadata| metadata.get("wtf-rlsr"))
+        {
+            Some(metadata) => metadata.clone(),
+            None => return PackageMetadata::default(),
+        };
+        match metadata.try_into() {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                warn!("Invalid {} metadata.wtf-rlsr: {}", pkg.name(), err);
+                PackageMetadata::default()
+            }
+        }
+    }
+}
+
+/// Creates git diff pathspecs of a package relative to repository.
+/// Uses `package.include` or target sources directories, always includes
+/// manifest and build script and excludes `package.exclude` globs.
+pub fn source_git_diff_paths<F>(pkg: &Package, rel_path: F) -> Vec<String>
+where
+    F: Fn(&Path) -> PathBuf,
+{
+    let root = rel_path(pkg.root());
+    let glob_spec = |glob: &str| root.join(glob.trim_start_matches('/')).clean_path_str();
+    let metadata = PackageMetadata::read(pkg);
+    let mut pathspecs: Vec<String> = if pkg.manifest().include().is_empty() {
+        pkg.targets()
+            .iter()
+            .filter(|target| !target.is_custom_build())
+            .filter_map(|target| target.src_path().path())
+            .map(|path| rel_path(path.parent().unwrap()).clean_path_str())
+            .collect()
+    } else {
+        pkg.manifest()
+            .include()
+            .iter()
+            .map(|glob| glob_spec(glob))
+            .collect()
+    };
+    pathspecs.push(rel_path(pkg.manifest_path()).clean_path_str());
+    pathspecs.extend(
+        pkg.targets()
+            .iter()
+            .filter(|target| target.is_custom_build())
+            .filter_map(|target| target.src_path().path())
+            .map(|path| rel_path(path).clean_path_str()),
+    );
+    pathspecs.extend(metadata.include.iter().map(|glob| glob_spec(glob)));
+    pathspecs.extend(
+        pkg.manifest()
+            .exclude()
+            .iter()
+            .chain(metadata.ignore.iter())
+            .map(|glob| format!("!{}", glob_spec(glob))),
+    );
+    pathspecs.sort();
+    pathspecs.dedup();
+    pathspecs
 }
 
 /// Creates a source code glob paths for package root.
@@ -0,0 +1,255 @@
+//! Release pre-flight checks.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use cargo::{
+    core::{Dependency, Source, SourceId},
+    sources::RegistrySource,
+    util::Config as CargoConfig,
+};
+use colored::Colorize;
+use failure::Error;
+use git2::RepositoryState;
+
+use crate::{
+    git::{self, RemoteAuth, RemoteBranchState, Repository},
+    ws::Workspace,
+};
+
+/// Environment variable with registry publish token.
+pub static PUBLISH_TOKEN_ENV: &str = "CCI";
+
+/// Manifests left by a failed release or a dry run.
+static LEFTOVERS: &[&str] = &[
+    "Cargo.backup.toml",
+    "Cargo.preview-head.toml",
+    "Cargo.preview-index.toml",
+];
+
+/// Pre-flight check result.
+#[derive(Debug)]
+pub struct PreflightCheck {
+    pub name: &'static str,
+    pub error: Option<String>,
+}
+
+/// Pre-flight checks run before any commit or publish.
+pub struct Preflight<'a> {
+    pub repo: &'a Repository,
+    pub workspace: &'a Workspace<'a>,
+    pub cargo_config: &'a CargoConfig,
+    pub cache_dir: &'a Path,
+    pub remote: &'a str,
+    pub branch: &'a str,
+    /// Packages with versions to be released.
+    pub targets: &'a [(String, semver::Version)],
+    /// Tags to be created.
+    pub tags: &'a [String],
+    /// Checks publish token and registry.
+    pub publish: bool,
+    /// Skips checks contacting the remote.
+    pub offline: bool,
+}
+
+impl<'a> Preflight<'a> {
+    /// Runs all checks.
+    pub fn run(&self, auth: &mut RemoteAuth) -> Vec<PreflightCheck> {
+        let mut checks = vec![
+            check("Branch", self.check_branch(auth)),
+            check("Tags", self.check_tags(auth)),
+            check("Leftover files", self.check_leftovers()),
+            check("Cache repository", self.check_cache()),
+        ];
+        if self.publish {
+            checks.push(check("Publish token", self.check_token()));
+            checks.push(check("Registry", self.check_registry()));
+        }
+        checks
+    }
+
+    /// Checks HEAD is on a branch up to date with the remote.
+    fn check_branch(&self, auth: &mut RemoteAuth) -> Result<(), Error> {
+        if !self.repo.head()?.is_branch() {
+            return Err(format_err!("HEAD is not on a branch"));
+        }
+        if self.offline {
+            return Ok(());
+        }
+        check_remote_branch(self.repo, self.remote, self.branch, auth)
+    }
+
+    /// Checks tags don't exist locally or on the remote.
+    fn check_tags(&self, auth: &mut RemoteAuth) -> Result<(), Error> {
+        let existing: Vec<&str> = self
+            .tags
+            .iter()
+            .filter(|tag| self.repo.find_reference(tag).is_ok())
+            .map(|tag| tag.as_str())
+            .collect();
+        if !existing.is_empty() {
+            return Err(format_err!("tags already exist: {}", existing.join(", ")));
+        }
+        if self.offline {
+            return Ok(());
+        }
+        check_remote_tags(self.repo, self.remote, self.tags, auth)
+    }
+
+    /// Checks there are no backup or preview manifests left
+    /// by a failed release or a dry run.
+    fn check_leftovers(&self) -> Result<(), Error> {
+        let mut dirs = vec![self.workspace.directory.clone()];
+        dirs.extend(
+            self.workspace
+                .packages()
+                .iter()
+                .map(|pkg| pkg.root().to_path_buf()),
+        );
+        let mut leftovers = Vec::new();
+        for dir in dirs {
+            for name in LEFTOVERS {
+                let path = dir.join(name);
+                if path.exists() {
+                    leftovers.push(path);
+                }
+            }
+        }
+        leftovers.sort();
+        leftovers.dedup();
+        if leftovers.is_empty() {
+            Ok(())
+        } else {
+            Err(format_err!("leftover files: {}", display_paths(&leftovers)))
+        }
+    }
+
+    /// Checks cache repository is clean if it exists.
+    fn check_cache(&self) -> Result<(), Error> {
+        if !self.cache_dir.exists() {
+            return Ok(());
+        }
+        let cache = Repository::open(self.cache_dir)?;
+        if cache.state() != RepositoryState::Clean {
+            return Err(format_err!("repository is in {:?} state", cache.state()));
+        }
+        if cache.index()?.has_conflicts() {
+            return Err(format_err!("repository index has conflicts"));
+        }
+        cache.head_commit()?;
+        Ok(())
+    }
+
+    /// Checks publish token is set.
+    fn check_token(&self) -> Result<(), Error> {
+        match std::env::var(PUBLISH_TOKEN_ENV) {
+            Ok(ref token) if !token.is_empty() => Ok(()),
+            _ => Err(format_err!("{} is not set", PUBLISH_TOKEN_ENV)),
+        }
+    }
+
+    /// Checks target versions are not in the registry index.
+    fn check_registry(&self) -> Result<(), Error> {
+        let _lock = self.cargo_config.acquire_package_cache_lock()?;
+        let source_id = SourceId::crates_io(self.cargo_config)?;
+        let mut source = RegistrySource::remote(source_id, &HashSet::new(), self.cargo_config);
+        source.update()?;
+        let mut existing = Vec::new();
+        for (name, version) in self.targets {
+            let requirement = format!("={}", version);
+            let dep = Dependency::parse_no_deprecated(name, Some(&requirement), source_id)?;
+            if !source.query_vec(&dep)?.is_empty() {
+                existing.push(format!("{}@{}", name, version));
+            }
+        }
+        if existing.is_empty() {
+            Ok(())
+        } else {
+            Err(format_err!("already published: {}", existing.join(", ")))
+        }
+    }
+}
+
+/// Checks remote branch didn't diverge from local branch.
+pub fn check_remote_branch(
+    repo: &Repository,
+    remote: &str,
+    branch: &str,
+    auth: &mut RemoteAuth,
+) -> Result<(), Error> {
+    let state = git::check_remote_branch(repo, remote, branch, auth)?;
+    trace!("Remote branch state: {:?}", state);
+    match state {
+        RemoteBranchState::Diverged { ahead, behind } => Err(format_err!(
+            "branch {} diverged from {}: {} local and {} remote commits",
+            branch,
+            remote,
+            ahead,
+            behind
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks tags don't exist on the remote.
+pub fn check_remote_tags(
+    repo: &Repository,
+    remote: &str,
+    tags: &[String],
+    auth: &mut RemoteAuth,
+) -> Result<(), Error> {
+    let remote_refs = git::list_remote_refs(repo, remote, auth)?;
+    let existing: Vec<&str> = tags
+        .iter()
+        .filter(|tag| remote_refs.iter().any(|(name, _)| name == *tag))
+        .map(|tag| tag.as_str())
+        .collect();
+    if existing.is_empty() {
+        Ok(())
+    } else {
+        Err(format_err!(
+            "tags already exist on {}: {}",
+            remote,
+            existing.join(", ")
+        ))
+    }
+}
+
+/// Prints checks results to stderr and returns an error if any of them failed.
+pub fn report_preflight(checks: &[PreflightCheck]) -> Result<(), Error> {
+    for check in checks {
+        match &check.error {
+            None => eprintln!("  {} {}", "✔".green(), check.name),
+            Some(error) => eprintln!("  {} {}: {}", "✘".red(), check.name, error),
+        }
+    }
+    let failed: Vec<&str> = checks
+        .iter()
+        .filter(|check| check.error.is_some())
+        .map(|check| check.name)
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format_err!(
+            "pre-flight checks failed: {}",
+            failed.join(", ")
+        ))
+    }
+}
+
+fn check(name: &'static str, result: Result<(), Error>) -> PreflightCheck {
+    trace!("Pre-flight check {}: {:?}", name, result);
+    PreflightCheck {
+        name,
+        error: result.err().map(|err| err.to_string()),
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -5,19 +5,60 @@ use cargo::{
     ops::{publish, PublishOpts},
     util::Config as CargoConfig,
 };
-use failure::Error;
+use failure::{Error, ResultExt};
 
 use crate::{
-    util::Logger,
+    util::{LocalRegistry, Logger, Report, PUBLISH_TOKEN_ENV},
     ws::{Package, Workspace},
 };
 
+/// Package publishing result.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PublishStatus {
+    Published,
+    AlreadyUploaded,
+    Failed,
+}
+
+impl PublishStatus {
+    /// Returns true if package is available in the registry.
+    pub fn is_ok(&self) -> bool {
+        match self {
+            PublishStatus::Published | PublishStatus::AlreadyUploaded => true,
+            PublishStatus::Failed => false,
+        }
+    }
+}
+
+/// Registry packages are published to.
+#[derive(Copy, Clone)]
+pub enum PublishTarget<'a> {
+    /// Default registry, nothing is uploaded on dry run.
+    Registry { dry_run: bool },
+    /// Local file-based registry.
+    Local(&'a LocalRegistry),
+}
+
 /// Publishes a package.
-pub fn publish_pkg(pkg: &CargoPackage, config: &CargoConfig, dry_run: bool) -> Result<bool, Error> {
+/// Local registry errors are returned, default registry errors are reported as failed status.
+pub fn publish_pkg(
+    pkg: &CargoPackage,
+    config: &CargoConfig,
+    target: PublishTarget<'_>,
+) -> Result<PublishStatus, Error> {
+    let dry_run = match target {
+        PublishTarget::Registry { dry_run } => dry_run,
+        PublishTarget::Local(registry) => {
+            let status = registry
+                .publish(pkg, config)
+                .with_context(|e| format!("Package {} local publish error: {}", pkg.name(), e))?;
+            return Ok(status);
+        }
+    };
     let pub_opts = PublishOpts {
         dry_run,
         config: config,
-        token: Some(std::env::var("CCI")?),
+        token: Some(std::env::var(PUBLISH_TOKEN_ENV)?),
         index: None,
         verify: false,
         allow_dirty: true,
@@ -31,12 +72,12 @@ pub fn publish_pkg(pkg: &CargoPackage, config: &CargoConfig, dry_run: bool) -> R
     let pkg_workspace = match CargoWorkspace::new(pkg.manifest_path(), config) {
         Ok(workspace) => workspace,
         Err(err) => {
-            println!(
+            eprintln!(
                 "Cannot open package {} workspace error: {:?}.",
                 pkg.name(),
                 err
             );
-            return Ok(false);
+            return Ok(PublishStatus::Failed);
         }
     };
     pkg_workspace.status("Publishing", pkg.name());
@@ -47,16 +88,16 @@ pub fn publish_pkg(pkg: &CargoPackage, config: &CargoConfig, dry_run: bool) -> R
                 "Publishing",
                 format!("Package {} already published.", pkg.name()),
             );
-            return Ok(true);
+            return Ok(PublishStatus::AlreadyUploaded);
         }
-        println!(
+        eprintln!(
             "Package {} publish returned with error: {:?}.",
             pkg.name(),
             err.to_string().lines().next().unwrap()
         );
-        Ok(false)
+        Ok(PublishStatus::Failed)
     } else {
-        Ok(true)
+        Ok(PublishStatus::Published)
     }
 }
 
@@ -67,10 +108,13 @@ pub fn publish_pkg_deep(
     config: &CargoConfig,
     update_packages: &Vec<&Package<'_>>,
     published: &mut Vec<String>,
-    dry_run: bool,
+    report: &mut Report,
+    target: PublishTarget<'_>,
 ) -> Result<bool, failure::Error> {
     let name = package.name().to_string();
-    if !publish_pkg(&package, &config, dry_run)? {
+    let status = publish_pkg(&package, &config, target)?;
+    report.publish_status(&name, status);
+    if !status.is_ok() {
         return Ok(false);
     }
     for (name, _) in workspace.graphs.dependants.edges(&name) {
@@ -85,7 +129,8 @@ pub fn publish_pkg_deep(
                 config,
                 update_packages,
                 published,
-                dry_run,
+                report,
+                target,
             )? {
                 return Ok(false);
             }
@@ -0,0 +1,137 @@
+//! Local file-based registry.
+//!
+//! Packages are stored as `<root>/<name>-<version>.crate` files,
+//! dependants are resolved against unpacked crates.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use cargo::{
+    core::{compiler::CompileMode, Package as CargoPackage, Workspace as CargoWorkspace},
+    ops::{self, CompileOptions, PackageOpts},
+    util::Config as CargoConfig,
+};
+use failure::{Error, ResultExt};
+use flate2::read::GzDecoder;
+
+use crate::{
+    util::{Logger, PublishStatus},
+    ws,
+};
+
+/// Local file-based registry used to test publishing.
+pub struct LocalRegistry {
+    root: PathBuf,
+}
+
+impl LocalRegistry {
+    /// Opens a registry, creates it if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        let root = root.as_ref().to_path_buf();
+        std::fs::create_dir_all(&root)?;
+        trace!("Local registry: {:?}", root);
+        Ok(LocalRegistry { root })
+    }
+
+    /// Returns registry root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Packages and publishes a package to the registry.
+    /// Existing version is replaced if package contents changed.
+    pub fn publish(
+        &self,
+        pkg: &CargoPackage,
+        config: &CargoConfig,
+    ) -> Result<PublishStatus, Error> {
+        let workspace = CargoWorkspace::new(pkg.manifest_path(), config)?;
+        let opts = PackageOpts {
+            config,
+            list: false,
+            check_metadata: false,
+            allow_dirty: true,
+            verify: false,
+            jobs: None,
+            target: None,
+            features: vec![],
+            all_features: true,
+            no_default_features: false,
+        };
+        let crate_file = ops::package(&workspace, &opts)?
+            .ok_or_else(|| format_err!("package {} was not packaged", pkg.name()))?;
+        let contents = std::fs::read(crate_file.path())?;
+        let crate_path = self.crate_path(pkg.name().as_str(), pkg.version());
+        let status = match std::fs::read(&crate_path) {
+            Ok(existing) if existing == contents => return Ok(PublishStatus::AlreadyUploaded),
+            Ok(_) => {
+                trace!(
+                    "Replacing {} v{} in local registry",
+                    pkg.name(),
+                    pkg.version()
+                );
+                PublishStatus::Published
+            }
+            Err(_) => PublishStatus::Published,
+        };
+        std::fs::write(crate_path, contents)?;
+        workspace.status(
+            "Published",
+            format!(
+                "{} v{} to {}",
+                pkg.name(),
+                pkg.version(),
+                self.root.display()
+            ),
+        );
+        Ok(status)
+    }
+
+    /// Checks that packages published in the registry resolve and build together.
+    ///
+    /// Creates a package depending on exact versions of packages with
+    /// unpacked registry crates patched in place of crates.io versions.
+    pub fn check(&self, packages: &[(String, semver::Version)]) -> Result<(), Error> {
+        let check_dir = self.root.join("check");
+        if check_dir.exists() {
+            std::fs::remove_dir_all(&check_dir)?;
+        }
+        let crates_dir = check_dir.join("crates");
+        std::fs::create_dir_all(&crates_dir)?;
+        std::fs::create_dir_all(check_dir.join("src"))?;
+        std::fs::write(check_dir.join("src").join("lib.rs"), "")?;
+
+        let mut dependencies = String::new();
+        let mut patches = String::new();
+        for (name, version) in packages {
+            let crate_path = self.crate_path(name, version);
+            let file = File::open(&crate_path)
+                .with_context(|e| format!("error opening {:?}: {}", crate_path, e))?;
+            tar::Archive::new(GzDecoder::new(file)).unpack(&crates_dir)?;
+            dependencies.push_str(&format!("{:?} = \"={}\"\n", name, version));
+            patches.push_str(&format!(
+                "{:?} = {{ path = \"crates/{}-{}\" }}\n",
+                name, name, version
+            ));
+        }
+        let manifest = format!(
+            "[package]\nname = \"wtf-rlsr-registry-check\"\nversion = \"0.0.0\"\n\
+             edition = \"2018\"\npublish = false\n\n[dependencies]\n{}\n\
+             [patch.crates-io]\n{}\n[workspace]\n",
+            dependencies, patches
+        );
+        std::fs::write(check_dir.join("Cargo.toml"), manifest)?;
+
+        let config = ws::cargo_config(check_dir);
+        let workspace = ws::cargo_workspace(&config)?;
+        ops::generate_lockfile(&workspace)?;
+        let opts = CompileOptions::new(&config, CompileMode::Check { test: false })?;
+        ops::compile(&workspace, &opts)?;
+        Ok(())
+    }
+
+    /// Returns path of a package crate file.
+    fn crate_path(&self, name: &str, version: &semver::Version) -> PathBuf {
+        self.root.join(format!("{}-{}.crate", name, version))
+    }
+}
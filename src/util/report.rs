@@ -0,0 +1,167 @@
+//! Release report.
+
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::util::PublishStatus;
+
+/// Release report output format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Markdown,
+}
+
+impl ReportFormat {
+    /// Guesses report format from a file extension.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("md") | Some("markdown") => ReportFormat::Markdown,
+            _ => ReportFormat::Json,
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            _ => Err(format_err!("unknown report format: {}", s)),
+        }
+    }
+}
+
+/// Bumped package entry.
+#[derive(Debug, Serialize)]
+pub struct BumpedPackage {
+    pub name: String,
+    pub old_version: String,
+    pub new_version: String,
+}
+
+/// Release stage timing.
+#[derive(Debug, Serialize)]
+pub struct Stage {
+    pub name: String,
+    pub seconds: f64,
+}
+
+/// Structured release report.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub root_package: Option<String>,
+    pub update: Option<String>,
+    pub bumped: Vec<BumpedPackage>,
+    pub commits: Vec<String>,
+    pub tags: Vec<String>,
+    pub published: Vec<String>,
+    pub skipped: Vec<String>,
+    pub stages: Vec<Stage>,
+    pub warnings: Vec<String>,
+    #[serde(skip)]
+    current: Option<(String, Instant)>,
+}
+
+impl Report {
+    /// Starts a new stage, finishing the current one.
+    pub fn stage(&mut self, name: &str) {
+        self.finish_stage();
+        self.current = Some((name.to_owned(), Instant::now()));
+    }
+
+    /// Finishes currently measured stage.
+    pub fn finish_stage(&mut self) {
+        if let Some((name, start)) = self.current.take() {
+            let seconds = start.elapsed().as_secs_f64();
+            trace!("Stage {} took {:.3}s", name, seconds);
+            self.stages.push(Stage { name, seconds });
+        }
+    }
+
+    /// Adds a warning to the report.
+    pub fn warn<T: ToString>(&mut self, message: T) {
+        self.warnings.push(message.to_string());
+    }
+
+    /// Adds a bumped package to the report.
+    pub fn bumped(&mut self, name: &str, old: &semver::Version, new: &semver::Version) {
+        if self.bumped.iter().any(|pkg| pkg.name == name) {
+            return;
+        }
+        self.bumped.push(BumpedPackage {
+            name: name.to_owned(),
+            old_version: old.to_string(),
+            new_version: new.to_string(),
+        });
+    }
+
+    /// Adds a publish result to the report.
+    pub fn publish_status(&mut self, name: &str, status: PublishStatus) {
+        match status {
+            PublishStatus::Published => self.published.push(name.to_owned()),
+            PublishStatus::AlreadyUploaded => self.skipped.push(name.to_owned()),
+            PublishStatus::Failed => self.warn(format!("Package {} was not published", name)),
+        }
+    }
+
+    /// Renders report in a given format.
+    pub fn render(&self, format: ReportFormat) -> Result<String, failure::Error> {
+        match format {
+            ReportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            ReportFormat::Markdown => Ok(self.to_markdown()),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut lines = vec!["# Release report".to_owned(), "".to_owned()];
+        if let Some(root) = &self.root_package {
+            lines.push(format!("* Package: `{}`", root));
+        }
+        if let Some(update) = &self.update {
+            lines.push(format!("* Update: {}", update));
+        }
+        if !self.bumped.is_empty() {
+            lines.push("".to_owned());
+            lines.push("## Bumped packages".to_owned());
+            lines.push("".to_owned());
+            lines.push("| Package | Old | New |".to_owned());
+            lines.push("| --- | --- | --- |".to_owned());
+            for pkg in &self.bumped {
+                lines.push(format!(
+                    "| {} | {} | {} |",
+                    pkg.name, pkg.old_version, pkg.new_version
+                ));
+            }
+        }
+        markdown_list(&mut lines, "Commits", &self.commits);
+        markdown_list(&mut lines, "Tags", &self.tags);
+        markdown_list(&mut lines, "Published", &self.published);
+        markdown_list(&mut lines, "Already uploaded", &self.skipped);
+        if !self.stages.is_empty() {
+            lines.push("".to_owned());
+            lines.push("## Stages".to_owned());
+            lines.push("".to_owned());
+            for stage in &self.stages {
+                lines.push(format!("* {}: {:.3}s", stage.name, stage.seconds));
+            }
+        }
+        markdown_list(&mut lines, "Warnings", &self.warnings);
+        lines.join("\n")
+    }
+}
+
+fn markdown_list(lines: &mut Vec<String>, title: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    lines.push("".to_owned());
+    lines.push(format!("## {}", title));
+    lines.push("".to_owned());
+    for item in items {
+        lines.push(format!("* `{}`", item));
+    }
+}
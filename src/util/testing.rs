@@ -21,7 +21,7 @@ pub fn run_tests(
             workspace.status("Testing", "Finished");
         }
         Err(err) => {
-            println!(
+            eprintln!(
                 "Package {} test returned with error: {}.",
                 package.name(),
                 err
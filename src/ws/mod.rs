@@ -1,19 +1,16 @@
 mod graphs;
 mod packages;
+mod version;
 pub use self::graphs::*;
 pub use self::packages::*;
+pub use self::version::*;
 
 use std::path::{Path, PathBuf};
 
-use failure::ResultExt;
-
 use cargo::core::{shell::Shell, Workspace as CargoWorkspace};
 use cargo::util::{config::Config as CargoConfig, errors::CargoResult};
 
-use crate::{
-    git::Repository,
-    util::{Bump, BumpExt},
-};
+use crate::{git::Repository, util::Bump};
 
 /// Creates default workspace configuration.
 pub fn cargo_config(dir: PathBuf) -> CargoConfig {
@@ -32,8 +29,8 @@ pub struct Workspace<'a> {
     pub graphs: WorkspaceGraphs,
     pub packages: Packages<'a>,
     pub directory: PathBuf,
-    // serialized package info
-    root_package: SerializedPackage,
+    // workspace name and version
+    root_version: WorkspaceVersion,
     manifest_path: PathBuf,
 }
 
@@ -44,32 +41,39 @@ impl<'a> Workspace<'a> {
         repo: &mut Repository,
     ) -> Result<Self, failure::Error> {
         let graphs = workspace_graph(&cargo);
-        let packages = Packages::new(cargo.members(), repo)?;
         let directory = cargo.config().cwd().to_path_buf();
-        let root_package = SerializedPackage::open(&directory.join("package.json"))?;
+        let root_version = WorkspaceVersion::open(&directory)?;
+        repo.set_workspace_version(root_version.version.clone());
+        let packages = Packages::new(cargo.members(), repo)?;
         let manifest_path = repo.rel_path(&directory.join("Cargo.toml"));
         trace!(
-            "Workspace {} version: {}",
-            root_package.name,
-            root_package.version
+            "Workspace {} version: {:?} ({:?})",
+            root_version.name,
+            root_version.version,
+            root_version.source
         );
         Ok(Workspace {
             graphs,
             packages,
             directory,
-            root_package,
+            root_version,
             manifest_path,
         })
     }
 
-    /// Returns workspace package version.
-    pub fn version(&self) -> &semver::Version {
-        &self.root_package.version
+    /// Returns workspace version if configured.
+    pub fn version(&self) -> Option<&semver::Version> {
+        self.root_version.version.as_ref()
     }
 
-    /// Returns workspace package name.
+    /// Returns workspace version source.
+    pub fn version_source(&self) -> VersionSource {
+        self.root_version.source
+    }
+
+    /// Returns workspace name.
     pub fn name(&self) -> &str {
-        &self.root_package.name
+        &self.root_version.name
     }
 
     /// Returns workspace packages.
@@ -82,10 +86,10 @@ impl<'a> Workspace<'a> {
         self.packages.find_by_name(name)
     }
 
-    /// Bumps workspace package version in file ONLY.
-    pub fn bump(&self, bump: Bump) -> Result<(), failure::Error> {
-        self.root_package
-            .bump_and_save(bump, &self.directory.join("package.json"))
+    /// Bumps workspace version in `package.json` or `VERSION` file ONLY.
+    /// Returns name of the changed file.
+    pub fn bump(&self, bump: Bump) -> Result<Option<PathBuf>, failure::Error> {
+        self.root_version.bump_and_save(bump, &self.directory)
     }
 
     /// Returns clean path to workspace manifest.
@@ -93,30 +97,3 @@ impl<'a> Workspace<'a> {
         self.manifest_path.as_ref()
     }
 }
-
-#[derive(Serialize, Deserialize, Clone)]
-struct SerializedPackage {
-    name: String,
-    version: semver::Version,
-}
-
-impl SerializedPackage {
-    fn open<P: AsRef<Path>>(path: P) -> Result<Self, failure::Error> {
-        let content = std::fs::read_to_string(path)
-            .with_context(|e| format!("error reading package.json: {}", e))?;
-        let package = serde_json::from_str(&content)?;
-        Ok(package)
-    }
-
-    fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), failure::Error> {
-        let content = serde_json::to_string_pretty(&self)?;
-        std::fs::write(path, content)?;
-        Ok(())
-    }
-
-    fn bump_and_save<P: AsRef<Path>>(&self, bump: Bump, path: P) -> Result<(), failure::Error> {
-        let mut manifest = self.clone();
-        manifest.version = manifest.version.bump(bump);
-        manifest.save(path)
-    }
-}
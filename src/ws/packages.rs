@@ -19,6 +19,8 @@ impl<'a> Packages<'a> {
         I: Iterator<Item = &'a CargoPackage>,
     {
         let members: Vec<_> = iter.collect();
+        // walk history once for all packages released without tags
+        repo.find_release_commits(&members)?;
         // pre-load git diff cache
         for member in &members {
             repo.diff(member)?;
@@ -0,0 +1,171 @@
+//! Workspace version sources.
+
+use std::path::{Path, PathBuf};
+
+use failure::ResultExt;
+
+use crate::util::{Bump, BumpExt};
+
+/// Source of a workspace version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionSource {
+    /// `package.json` in workspace root.
+    PackageJson,
+    /// `[workspace.package] version` in workspace manifest.
+    WorkspacePackage,
+    /// `[workspace.metadata] version` in workspace manifest.
+    WorkspaceMetadata,
+    /// Plain `VERSION` file in workspace root.
+    VersionFile,
+    /// No workspace version, packages are tagged separately.
+    None,
+}
+
+impl VersionSource {
+    /// Returns workspace manifest table containing version.
+    pub fn manifest_table(&self) -> Option<&'static str> {
+        match self {
+            VersionSource::WorkspacePackage => Some("workspace.package"),
+            VersionSource::WorkspaceMetadata => Some("workspace.metadata"),
+            _ => None,
+        }
+    }
+
+    /// Returns name of a file containing version, other than manifest.
+    pub fn file_name(&self) -> Option<&'static str> {
+        match self {
+            VersionSource::PackageJson => Some("package.json"),
+            VersionSource::VersionFile => Some("VERSION"),
+            _ => None,
+        }
+    }
+}
+
+/// Workspace name and version.
+#[derive(Debug, Clone)]
+pub struct WorkspaceVersion {
+    pub name: String,
+    pub version: Option<semver::Version>,
+    pub source: VersionSource,
+}
+
+impl WorkspaceVersion {
+    /// Reads workspace version from the first source found in directory.
+    pub fn open(directory: &Path) -> Result<Self, failure::Error> {
+        let package_json = directory.join("package.json");
+        if package_json.exists() {
+            let package = SerializedPackage::open(&package_json)?;
+            return Ok(WorkspaceVersion {
+                name: package.name,
+                version: Some(package.version),
+                source: VersionSource::PackageJson,
+            });
+        }
+        let manifest = read_manifest(&directory.join("Cargo.toml"))?;
+        let name = manifest_str(&manifest, &["workspace", "metadata", "name"])
+            .or_else(|| {
+                directory
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_default();
+        let manifest_sources = &[
+            VersionSource::WorkspacePackage,
+            VersionSource::WorkspaceMetadata,
+        ];
+        for source in manifest_sources {
+            let table = source.manifest_table().unwrap();
+            let mut path: Vec<&str> = table.split('.').collect();
+            path.push("version");
+            if let Some(version) = manifest_str(&manifest, &path) {
+                return Ok(WorkspaceVersion {
+                    name,
+                    version: Some(parse_version(&version, table)?),
+                    source: *source,
+                });
+            }
+        }
+        let version_file = directory.join("VERSION");
+        if version_file.exists() {
+            let content = std::fs::read_to_string(&version_file)
+                .with_context(|e| format!("error reading VERSION: {}", e))?;
+            return Ok(WorkspaceVersion {
+                name,
+                version: Some(parse_version(content.trim(), "VERSION")?),
+                source: VersionSource::VersionFile,
+            });
+        }
+        Ok(WorkspaceVersion {
+            name,
+            version: None,
+            source: VersionSource::None,
+        })
+    }
+
+    /// Bumps version in a file source and returns its name.
+    /// Manifest sources are bumped by the manifest updater.
+    pub fn bump_and_save(
+        &self,
+        bump: Bump,
+        directory: &Path,
+    ) -> Result<Option<PathBuf>, failure::Error> {
+        let version = match &self.version {
+            Some(version) => version.bump(bump),
+            None => return Ok(None),
+        };
+        let file_name = match self.source.file_name() {
+            Some(file_name) => PathBuf::from(file_name),
+            None => return Ok(None),
+        };
+        let path = directory.join(&file_name);
+        match self.source {
+            VersionSource::PackageJson => {
+                let mut package = SerializedPackage::open(&path)?;
+                package.version = version;
+                package.save(&path)?;
+            }
+            _ => std::fs::write(&path, format!("{}\n", version))?,
+        }
+        Ok(Some(file_name))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct SerializedPackage {
+    name: String,
+    version: semver::Version,
+}
+
+impl SerializedPackage {
+    fn open<P: AsRef<Path>>(path: P) -> Result<Self, failure::Error> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|e| format!("error reading package.json: {}", e))?;
+        let package = serde_json::from_str(&content)?;
+        Ok(package)
+    }
+
+    fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), failure::Error> {
+        let content = serde_json::to_string_pretty(&self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<toml::Value, failure::Error> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|e| format!("error reading workspace manifest: {}", e))?;
+    Ok(toml::from_str(&content)?)
+}
+
+fn manifest_str(manifest: &toml::Value, path: &[&str]) -> Option<String> {
+    let mut value = manifest;
+    for key in path {
+        value = value.get(key)?;
+    }
+    value.as_str().map(|s| s.to_owned())
+}
+
+fn parse_version(version: &str, source: &str) -> Result<semver::Version, failure::Error> {
+    semver::Version::parse(version)
+        .map_err(|e| format_err!("invalid version {:?} in {}: {}", version, source, e))
+}
@@ -0,0 +1,141 @@
+//! Temporary workspace fixture for integration tests.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use git2::{Oid, Repository, Signature};
+use tempfile::TempDir;
+
+/// Returns path of the `wtf-rlsr` binary built for tests.
+pub fn bin() -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    path.join(format!("wtf-rlsr{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Temporary workspace with a bare remote and a cache repository.
+pub struct Fixture {
+    pub dir: TempDir,
+}
+
+impl Fixture {
+    /// Creates workspace with files committed and pushed to the remote.
+    pub fn new(files: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Fixture { dir };
+        Repository::init_bare(fixture.remote()).unwrap();
+
+        let ws = fixture.workspace();
+        write(&ws.join(".gitignore"), "target\nCargo.lock\n");
+        for (path, contents) in files {
+            write(&ws.join(path), contents);
+        }
+
+        let repo = Repository::init(&ws).unwrap();
+        configure(&repo);
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(&["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        repo.remote("origin", fixture.remote().to_str().unwrap())
+            .unwrap();
+        let branch = fixture.branch();
+        let refspec = format!("refs/heads/{}:refs/heads/{}", branch, branch);
+        repo.find_remote("origin")
+            .unwrap()
+            .push(&[refspec.as_str()], None)
+            .unwrap();
+
+        let cache = Repository::clone(ws.to_str().unwrap(), fixture.cache()).unwrap();
+        configure(&cache);
+        fixture
+    }
+
+    pub fn workspace(&self) -> PathBuf {
+        self.dir.path().join("workspace")
+    }
+
+    pub fn remote(&self) -> PathBuf {
+        self.dir.path().join("remote.git")
+    }
+
+    pub fn cache(&self) -> PathBuf {
+        self.dir.path().join("cache")
+    }
+
+    pub fn repo(&self) -> Repository {
+        Repository::open(self.workspace()).unwrap()
+    }
+
+    pub fn branch(&self) -> String {
+        let repo = self.repo();
+        let head = repo.head().unwrap();
+        head.shorthand().unwrap().to_owned()
+    }
+
+    pub fn head(&self) -> Oid {
+        self.repo().head().unwrap().target().unwrap()
+    }
+
+    /// Writes a file in workspace.
+    pub fn write(&self, path: &str, contents: &str) {
+        write(&self.workspace().join(path), contents);
+    }
+
+    /// Reads a file in workspace.
+    pub fn read(&self, path: &str) -> String {
+        std::fs::read_to_string(self.workspace().join(path)).unwrap()
+    }
+
+    /// Runs the binary in workspace with global options and arguments.
+    pub fn run(&self, global: &[&str], args: &[&str]) -> Output {
+        Command::new(bin())
+            .current_dir(self.workspace())
+            .arg(self.workspace())
+            .arg("-c")
+            .arg(self.cache())
+            .args(global)
+            .args(args)
+            .output()
+            .unwrap()
+    }
+}
+
+fn configure(repo: &Repository) {
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "Test").unwrap();
+    config.set_str("user.email", "test@example.com").unwrap();
+    config.set_bool("commit.gpgsign", false).unwrap();
+}
+
+pub fn write(path: &Path, contents: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+/// Asserts command succeeded, prints its output otherwise.
+pub fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "command failed\nstdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Returns contents of a file in `HEAD` commit.
+pub fn head_contents(repo: &Repository, path: &str) -> String {
+    let tree = repo.head().unwrap().peel_to_tree().unwrap();
+    let entry = tree.get_path(Path::new(path)).unwrap();
+    let blob = repo.find_blob(entry.id()).unwrap();
+    String::from_utf8(blob.content().to_vec()).unwrap()
+}
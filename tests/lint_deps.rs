@@ -0,0 +1,77 @@
+//! Lint dependencies integration tests.
+
+mod common;
+
+use common::Fixture;
+
+const A_MANIFEST: &str = "[package]\nname = \"a\"\nversion = \"0.1.0\"\nedition = \"2018\"\n";
+const A_LIB: &str = "pub fn value() -> u32 {\n    1\n}\n";
+
+/// Package `b` depending on `a` renamed as `alias`.
+const B_MANIFEST: &str = "[package]\nname = \"b\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+                          [dependencies]\n\
+                          alias = { package = \"a\", path = \"../a\", version = \"0.1.0\" }\n";
+const B_LIB: &str = "pub fn value() -> u32 {\n    alias::value()\n}\n";
+
+#[test]
+fn renamed_dependency_is_used() {
+    let fixture = Fixture::new(&[
+        ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+        ("a/Cargo.toml", A_MANIFEST),
+        ("a/src/lib.rs", A_LIB),
+        ("b/Cargo.toml", B_MANIFEST),
+        ("b/src/lib.rs", B_LIB),
+    ]);
+
+    let output = fixture.run(&[], &["lint-deps", "--fix"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    common::assert_success(&output);
+    assert!(stdout.contains("No dependency issues"), "{}", stdout);
+    assert_eq!(fixture.read("b/Cargo.toml"), B_MANIFEST);
+}
+
+#[test]
+fn fix_adds_test_only_dependency_to_dev_dependencies() {
+    let fixture = Fixture::new(&[
+        (
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"a\", \"b\", \"c\"]\n",
+        ),
+        ("a/Cargo.toml", A_MANIFEST),
+        ("a/src/lib.rs", A_LIB),
+        ("b/Cargo.toml", B_MANIFEST),
+        ("b/src/lib.rs", B_LIB),
+        (
+            "c/Cargo.toml",
+            "[package]\nname = \"c\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+             [dependencies]\nb = { path = \"../b\", version = \"0.1.0\" }\n",
+        ),
+        ("c/src/lib.rs", "pub fn value() -> u32 {\n    0\n}\n"),
+        (
+            "c/tests/value.rs",
+            "#[test]\nfn value() {\n    assert_eq!(a::value(), 1);\n}\n",
+        ),
+    ]);
+
+    let output = fixture.run(&[], &["lint-deps"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(stdout.contains("unused"), "{}", stdout);
+    assert!(stdout.contains("undeclared dev"), "{}", stdout);
+
+    let output = fixture.run(&[], &["lint-deps", "--fix"]);
+    common::assert_success(&output);
+    assert_eq!(fixture.read("b/Cargo.toml"), B_MANIFEST);
+    let manifest = fixture.read("c/Cargo.toml");
+    assert!(!manifest.contains("b = "), "{}", manifest);
+    let dev = manifest.find("[dev-dependencies]").expect(&manifest);
+    assert!(manifest[dev..].contains("a = { version = \"0.1.0\", path = \"../a\" }"));
+    for package in &["b", "c"] {
+        let dir = fixture.workspace().join(package);
+        assert!(!dir.join("Cargo.preview-head.toml").exists());
+        assert!(!dir.join("Cargo.preview-index.toml").exists());
+    }
+
+    let output = fixture.run(&[], &["lint-deps"]);
+    common::assert_success(&output);
+}
@@ -0,0 +1,299 @@
+//! Release integration tests driving the binary with scripted answers.
+
+mod common;
+
+use std::process::Output;
+
+use git2::Repository;
+
+use common::{assert_success, head_contents, Fixture};
+
+const A_MANIFEST: &str = "[package]\nname = \"a\"\nversion = \"0.1.0\"\nedition = \"2018\"\n";
+const B_MANIFEST: &str = "[package]\nname = \"b\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+                          [dependencies]\na = { path = \"../a\", version = \"0.1.0\" }\n";
+
+/// Creates workspace with package `a` and its dependant `b`.
+fn fixture() -> Fixture {
+    Fixture::new(&[
+        ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+        ("a/Cargo.toml", A_MANIFEST),
+        ("a/src/lib.rs", "pub fn value() -> u32 {\n    1\n}\n"),
+        ("b/Cargo.toml", B_MANIFEST),
+        (
+            "b/src/lib.rs",
+            "pub fn value() -> u32 {\n    a::value()\n}\n",
+        ),
+    ])
+}
+
+/// Runs `release` with answers in a JSON file.
+fn release(fixture: &Fixture, answers: &str) -> Output {
+    run(
+        fixture,
+        answers,
+        &["release", "--skip-tests", "--no-publish"],
+    )
+}
+
+/// Runs a command with answers in a JSON file.
+fn run(fixture: &Fixture, answers: &str, args: &[&str]) -> Output {
+    let answers_path = fixture.dir.path().join("answers.json");
+    common::write(&answers_path, answers);
+    fixture.run(&["--answers", answers_path.to_str().unwrap()], args)
+}
+
+/// Answers releasing a patch of `a` with `b` updated in tree.
+const PATCH_A: &str = r#"[
+    {"prompt": "Pick a package to commit", "answer": "a"},
+    {"prompt": "Select update kind for a", "answer": "patch"},
+    {"prompt": "Select dependencies to update in tree", "answer": ["b"]},
+    {"prompt": "Do you want to see git diff?", "answer": false},
+    {"prompt": "Commit header", "answer": "new value"},
+    {"prompt": "Commit message", "answer": null}
+]"#;
+
+#[test]
+fn release_patch_with_dependant() {
+    let fixture = fixture();
+    let initial = fixture.head();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+
+    let output = release(&fixture, PATCH_A);
+    assert_success(&output);
+
+    let repo = fixture.repo();
+    assert_ne!(fixture.head(), initial);
+    assert!(head_contents(&repo, "a/Cargo.toml").contains("version = \"0.1.1\""));
+    assert_eq!(
+        head_contents(&repo, "a/src/lib.rs"),
+        "pub fn value() -> u32 {\n    2\n}\n"
+    );
+    assert!(head_contents(&repo, "b/Cargo.toml").contains("version = \"0.1.1\""));
+
+    let remote = Repository::open_bare(fixture.remote()).unwrap();
+    assert!(remote.find_reference("refs/tags/a-v0.1.1").is_ok());
+    let branch = format!("refs/heads/{}", fixture.branch());
+    assert_eq!(remote.refname_to_id(&branch).unwrap(), fixture.head());
+}
+
+#[test]
+fn release_twice() {
+    let fixture = fixture();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+    assert_success(&release(&fixture, PATCH_A));
+    let first = fixture.head();
+
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    3\n}\n");
+    let output = release(&fixture, PATCH_A);
+    assert_success(&output);
+    assert_ne!(fixture.head(), first);
+
+    let repo = fixture.repo();
+    assert!(head_contents(&repo, "a/Cargo.toml").contains("version = \"0.1.2\""));
+    assert!(head_contents(&repo, "b/Cargo.toml").contains("version = \"0.1.2\""));
+    for package in &["", "a", "b"] {
+        let dir = fixture.workspace().join(package);
+        for leftover in &[
+            "Cargo.backup.toml",
+            "Cargo.preview-head.toml",
+            "Cargo.preview-index.toml",
+        ] {
+            assert!(!dir.join(leftover).exists(), "{:?}", dir.join(leftover));
+        }
+    }
+
+    let remote = Repository::open_bare(fixture.remote()).unwrap();
+    assert!(remote.find_reference("refs/tags/a-v0.1.1").is_ok());
+    assert!(remote.find_reference("refs/tags/a-v0.1.2").is_ok());
+}
+
+#[test]
+fn release_push_dry_run() {
+    let fixture = fixture();
+    let initial = fixture.head();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+
+    let output = run(
+        &fixture,
+        PATCH_A,
+        &[
+            "release",
+            "--skip-tests",
+            "--push-dry-run",
+            "--report",
+            "json",
+        ],
+    );
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("refs/tags/a-v0.1.1"));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["published"], serde_json::json!([]));
+    assert_eq!(report["skipped"], serde_json::json!([]));
+    assert_eq!(report["tags"], serde_json::json!([]));
+    let stages = report["stages"].as_array().unwrap();
+    assert!(stages.iter().all(|stage| stage["name"] != "Publishing"));
+    assert!(fixture.repo().find_reference("refs/tags/a-v0.1.1").is_err());
+    let cache = Repository::open(fixture.cache()).unwrap();
+    assert!(cache.find_reference("refs/tags/a-v0.1.1").is_err());
+
+    let remote = Repository::open_bare(fixture.remote()).unwrap();
+    assert!(remote.find_reference("refs/tags/a-v0.1.1").is_err());
+    let branch = format!("refs/heads/{}", fixture.branch());
+    assert_eq!(remote.refname_to_id(&branch).unwrap(), initial);
+}
+
+#[test]
+fn release_rejects_leftover_previews() {
+    let fixture = fixture();
+    let initial = fixture.head();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+    fixture.write("a/Cargo.preview-index.toml", A_MANIFEST);
+
+    let output = release(&fixture, PATCH_A);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Cargo.preview-index.toml"));
+    assert_eq!(fixture.head(), initial);
+}
+
+#[test]
+fn release_json_report() {
+    let fixture = fixture();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+
+    let output = run(
+        &fixture,
+        PATCH_A,
+        &[
+            "release",
+            "--skip-tests",
+            "--no-publish",
+            "--report",
+            "json",
+        ],
+    );
+    assert_success(&output);
+    // Stdout holds the report alone
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    assert!(stdout.starts_with('{'), "{}", stdout);
+    assert!(stdout.trim_end().ends_with('}'), "{}", stdout);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["root_package"], "a");
+    assert_eq!(report["update"], "patch");
+    assert_eq!(report["tags"], serde_json::json!(["refs/tags/a-v0.1.1"]));
+    let bumped: Vec<_> = report["bumped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pkg| {
+            (
+                pkg["name"].as_str().unwrap(),
+                pkg["new_version"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(bumped, [("a", "0.1.1"), ("b", "0.1.1")]);
+    let head = fixture.head().to_string();
+    let commits = report["commits"].as_array().unwrap();
+    assert_eq!(commits.last().unwrap(), &serde_json::json!(head));
+}
+
+#[test]
+fn release_failed_report_file() {
+    let fixture = fixture();
+    let initial = fixture.head();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+    let report_path = fixture.dir.path().join("report.md");
+
+    let output = run(
+        &fixture,
+        r#"[
+            {"prompt": "Pick a package to commit", "answer": "a"},
+            {"prompt": "Commit header", "answer": "new value"}
+        ]"#,
+        &[
+            "release",
+            "--skip-tests",
+            "--no-publish",
+            "--report-file",
+            report_path.to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    assert_eq!(fixture.head(), initial);
+    let report = std::fs::read_to_string(&report_path).unwrap();
+    assert!(report.starts_with("# Release report"), "{}", report);
+    assert!(report.contains("* Package: `a`"), "{}", report);
+    assert!(report.contains("Release failed"), "{}", report);
+}
+
+#[test]
+fn release_to_local_registry() {
+    let fixture = fixture();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+    let registry = fixture.dir.path().join("registry");
+
+    let output = run(
+        &fixture,
+        PATCH_A,
+        &[
+            "release",
+            "--skip-tests",
+            "--local-registry",
+            registry.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    assert!(registry.join("a-0.1.1.crate").exists());
+    assert!(registry.join("b-0.1.1.crate").exists());
+    let remote = Repository::open_bare(fixture.remote()).unwrap();
+    assert!(remote.find_reference("refs/tags/a-v0.1.1").is_ok());
+}
+
+#[test]
+fn release_test_changes_nothing() {
+    let fixture = fixture();
+    let initial = fixture.head();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+
+    let output = run(&fixture, PATCH_A, &["release-test"]);
+    assert_success(&output);
+    assert_eq!(fixture.head(), initial);
+    assert_eq!(fixture.read("a/Cargo.toml"), A_MANIFEST);
+    assert_eq!(fixture.read("b/Cargo.toml"), B_MANIFEST);
+    let registry = fixture.cache().join("target").join("wtf-rlsr-registry");
+    assert!(registry.join("a-0.1.1.crate").exists());
+    let remote = Repository::open_bare(fixture.remote()).unwrap();
+    assert!(remote.find_reference("refs/tags/a-v0.1.1").is_err());
+}
+
+#[test]
+fn release_cancelled_selection() {
+    let fixture = fixture();
+    let initial = fixture.head();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+
+    let output = release(
+        &fixture,
+        r#"[{"prompt": "Pick a package to commit", "answer": null}]"#,
+    );
+    assert_success(&output);
+    assert_eq!(fixture.head(), initial);
+}
+
+#[test]
+fn release_unexpected_prompt() {
+    let fixture = fixture();
+    let initial = fixture.head();
+    fixture.write("a/src/lib.rs", "pub fn value() -> u32 {\n    2\n}\n");
+
+    let output = release(
+        &fixture,
+        r#"[
+            {"prompt": "Pick a package to commit", "answer": "a"},
+            {"prompt": "Commit header", "answer": "new value"}
+        ]"#,
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected prompt"));
+    assert_eq!(fixture.head(), initial);
+}